@@ -0,0 +1,220 @@
+//! Parsing throughput on a generated graph with a million edges, compared
+//! with the parser the crate had before reading the big files in a single
+//! pass, which the redesign was meant to make at least 10 times faster.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::Duration;
+use std::time::Instant;
+
+use graphtlp::Graph;
+
+const NB_NODES: usize = 200_000;
+const NB_EDGES: usize = 1_000_000;
+const RUNS: usize = 10;
+/// How many times faster than the baseline the parse without copies must be.
+const TARGET_SPEEDUP: f64 = 10.;
+
+/// Deterministic pseudo random numbers (xorshift), to avoid depending on `rand`.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// A file as Tulip writes it: the edges, then a layout and a label for each node.
+fn generate(nb_nodes: usize, nb_edges: usize) -> String {
+    let mut rng = XorShift(0x5eed);
+    let mut tlp = String::with_capacity(40 * (nb_nodes + nb_edges));
+
+    writeln!(tlp, "(tlp \"2.3\"").unwrap();
+    writeln!(tlp, "(date \"02-25-2025\")").unwrap();
+    writeln!(tlp, "(comments \"Generated for the parsing benchmark.\")").unwrap();
+    writeln!(tlp, "(nb_nodes {nb_nodes})").unwrap();
+    writeln!(tlp, ";(nodes <node_id> <node_id> ...)").unwrap();
+    writeln!(tlp, "(nodes 0..{})", nb_nodes - 1).unwrap();
+    writeln!(tlp, "(nb_edges {nb_edges})").unwrap();
+    writeln!(tlp, ";(edge <edge_id> <source_id> <target_id>)").unwrap();
+    for id in 0..nb_edges {
+        writeln!(tlp, "(edge {id} {} {})", rng.below(nb_nodes), rng.below(nb_nodes)).unwrap();
+    }
+
+    writeln!(tlp, "(property  0 layout \"viewLayout\"").unwrap();
+    writeln!(tlp, "(default \"(0,0,0)\" \"()\")").unwrap();
+    for id in 0..nb_nodes {
+        writeln!(tlp, "(node {id} \"({},-{},0)\")", rng.below(1000), rng.below(1000)).unwrap();
+    }
+    writeln!(tlp, ")").unwrap();
+
+    writeln!(tlp, "(property  0 string \"viewLabel\"").unwrap();
+    writeln!(tlp, "(default \"\" \"\")").unwrap();
+    for id in 0..nb_nodes {
+        writeln!(tlp, "(node {id} \"node {id}\")").unwrap();
+    }
+    writeln!(tlp, ")").unwrap();
+    writeln!(tlp, ")").unwrap();
+
+    tlp
+}
+
+/// The combinators the crate used before, restricted to the blocks of the
+/// generated files: they copy every string and backtrack on each line.
+mod baseline {
+    use winnow::ascii::alpha1;
+    use winnow::ascii::dec_uint;
+    use winnow::ascii::multispace0;
+    use winnow::ascii::multispace1;
+    use winnow::ascii::space0;
+    use winnow::ascii::space1;
+    use winnow::ascii::take_escaped;
+    use winnow::combinator::alt;
+    use winnow::combinator::delimited;
+    use winnow::combinator::not;
+    use winnow::combinator::opt;
+    use winnow::combinator::repeat;
+    use winnow::combinator::separated;
+    use winnow::combinator::terminated;
+    use winnow::error::ContextError;
+    use winnow::error::ErrMode;
+    use winnow::prelude::*;
+    use winnow::token::take_until;
+    use winnow::token::take_while;
+
+    pub struct Graph {
+        pub nb_nodes: usize,
+        pub edges: Vec<(usize, usize, usize)>,
+        pub properties: Vec<(String, Vec<(usize, String)>)>
+    }
+
+    fn parse_tag<'a, O>(tag: &'static str, mut f: impl Parser<&'a str, O, ErrMode<ContextError>>) -> impl Parser<&'a str, O, ErrMode<ContextError>> {
+        move |input: &mut &'a str| {
+            ('(', space0, tag, space1).parse_next(input)?;
+            let res = f.parse_next(input)?;
+            (space0, opt(')')).parse_next(input)?;
+            Ok(res)
+        }
+    }
+
+    fn ids_len(input: &mut &str) -> ModalResult<usize> {
+        let range = (dec_uint::<_, usize, _>, "..", dec_uint::<_, usize, _>)
+            .map(|(start, _, end)| end - start + 1);
+        let list = separated(1.., terminated(dec_uint::<_, usize, _>, not("..")), multispace1)
+            .map(|ids: Vec<usize>| ids.len());
+        separated(1.., alt((range, list)), multispace1)
+            .map(|lens: Vec<usize>| lens.iter().sum())
+            .parse_next(input)
+    }
+
+    fn string(input: &mut &str) -> ModalResult<String> {
+        alt(("\"\"".map(|_| String::new()),
+            delimited('"', take_escaped(alt((alpha1, space1, take_while(1.., |c| c != '"'))), '\\', '"'), '"')
+                .map(|s: &str| s.to_owned())
+        )).parse_next(input)
+    }
+
+    fn comment(input: &mut &str) -> ModalResult<()> {
+        terminated(opt((';', take_until(.., '\n'))), multispace0).void().parse_next(input)
+    }
+
+    fn edge(input: &mut &str) -> ModalResult<(usize, usize, usize)> {
+        parse_tag("edge", (
+            terminated(dec_uint, space1),
+            terminated(dec_uint, space1),
+            dec_uint
+        )).parse_next(input)
+    }
+
+    fn property(input: &mut &str) -> ModalResult<(String, Vec<(usize, String)>)> {
+        let node = parse_tag("node", (terminated(dec_uint, multispace1), terminated(string, multispace0)));
+        parse_tag("property", (
+            delimited(multispace0, dec_uint::<_, usize, _>, multispace1),
+            terminated(alt(("layout", "string")), multispace1),
+            terminated(string, multispace1),
+            terminated(parse_tag("default", (terminated(string, multispace1), terminated(string, multispace0))), multispace1),
+            repeat(.., terminated(node, multispace0))
+        )).map(|(_, _, name, _, nodes)| (name, nodes))
+            .parse_next(input)
+    }
+
+    fn graph(input: &mut &str) -> ModalResult<Graph> {
+        let inner = |input: &mut &str| {
+            terminated(string, multispace0).parse_next(input)?;
+            opt(terminated(parse_tag("date", string), multispace0)).parse_next(input)?;
+            opt(terminated(parse_tag("comments", string), multispace0)).parse_next(input)?;
+            opt(terminated(parse_tag("nb_nodes", dec_uint::<_, usize, _>), multispace1)).parse_next(input)?;
+            comment(input)?;
+            let nb_nodes = terminated(parse_tag("nodes", ids_len), multispace0).parse_next(input)?;
+            opt(delimited(multispace0, parse_tag("nb_edges", dec_uint::<_, usize, _>), multispace0)).parse_next(input)?;
+            comment(input)?;
+            let edges = terminated(separated(.., edge, multispace0), multispace0).parse_next(input)?;
+            let properties = repeat(.., terminated(property, multispace0)).parse_next(input)?;
+            Ok(Graph { nb_nodes, edges, properties })
+        };
+        terminated(parse_tag("tlp", inner), multispace0).parse_next(input)
+    }
+
+    pub fn parse(input: &str) -> Result<Graph, String> {
+        graph.parse(input).map_err(|e| e.to_string())
+    }
+}
+
+/// Time `parse`, checking it read the whole graph from the numbers of nodes,
+/// edges and properties it returns.
+fn run(tlp: &str, parse: impl Fn(&str) -> [usize; 3]) -> Duration {
+    let start = Instant::now();
+    let counts = parse(black_box(tlp));
+    let elapsed = start.elapsed();
+    assert_eq!(counts, [NB_NODES, NB_EDGES, 2]);
+    elapsed
+}
+
+fn counts(graph: Graph) -> [usize; 3] {
+    let properties = ["viewLayout", "viewLabel"].iter().filter(|name| graph.property(name).is_some()).count();
+    [graph.nodes().len(), graph.edges_iter().count(), properties]
+}
+
+fn main() {
+    let tlp = generate(NB_NODES, NB_EDGES);
+
+    type Parse = fn(&str) -> [usize; 3];
+    let parsers: [(&str, Parse); 3] = [
+        ("baseline", |tlp| {
+            let graph = baseline::parse(tlp).unwrap();
+            [graph.nb_nodes, graph.edges.len(), graph.properties.len()]
+        }),
+        ("owned", |tlp| counts(tlp.parse().unwrap())),
+        ("borrowed", |tlp| counts(Graph::parse_borrowed(tlp).unwrap())),
+    ];
+
+    // the runs are interleaved, so that the load of the machine weighs the same on each parser
+    let mut best = [Duration::MAX; 3];
+    for _ in 0..RUNS {
+        for ((_, parse), time) in parsers.iter().zip(&mut best) {
+            *time = (*time).min(run(&tlp, parse));
+        }
+    }
+
+    let mb = tlp.len() as f64 / 1_000_000.;
+    println!("{NB_NODES} nodes, {NB_EDGES} edges ({mb:.1} MB), best of {RUNS} runs:");
+    for ((name, _), time) in parsers.iter().zip(&best) {
+        println!(
+            "{name:>10}: {time:>12.2?} ({:>4.0} MB/s), {:>5.1}x the baseline",
+            mb / time.as_secs_f64(),
+            best[0].as_secs_f64() / time.as_secs_f64()
+        );
+    }
+
+    let speedup = best[0].as_secs_f64() / best[2].as_secs_f64();
+    assert!(speedup >= TARGET_SPEEDUP, "the parse without copies is {speedup:.1}x the baseline, below the {TARGET_SPEEDUP}x target");
+}
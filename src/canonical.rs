@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+
+use crate::Attributes;
+use crate::Cluster;
+use crate::Graph;
+use crate::Property;
+use crate::PropertyType;
+use crate::Value;
+use crate::WriteOptions;
+
+/// Rewrite a tlp file in its canonical form, see [Graph::canonicalize].
+pub fn format(input: &str) -> Result<String, String> {
+    let mut graph = Graph::parse_borrowed(input)?;
+    graph.canonicalize();
+    let mut formatted = String::new();
+    graph.write_with(&mut formatted, &WriteOptions::default())?;
+    Ok(formatted)
+}
+
+impl Graph<'_> {
+    /// Put the graph in a canonical form, so that two graphs with the same
+    /// content are written byte for byte the same:
+    ///
+    /// - the identifiers are compacted into ranges, see [crate::Ids::compact],
+    ///   and the edges and clusters are sorted by identifier;
+    /// - the properties are sorted by graph then by name, their values by
+    ///   identifier, and the values equal to the default are dropped;
+    /// - the attributes are sorted by name;
+    /// - the numbers are written in their shortest form, `"2.50"` becoming `"2.5"`.
+    ///
+    /// The [crate::SourceMap] no longer matches the graph and is dropped.
+    pub fn canonicalize(&mut self) {
+        self.nodes.0.compact();
+        self.edges.0.sort_by_key(|e| e.id);
+
+        if let Some(clusters) = &mut self.clusters {
+            clusters.0.sort_by_key(|c| c.id);
+            clusters.0.iter_mut().for_each(Cluster::canonicalize);
+        }
+
+        if let Some(properties) = &mut self.properties {
+            properties.0.sort_by(|a, b| (a.graph_id, &a.name).cmp(&(b.graph_id, &b.name)));
+            properties.0.iter_mut().for_each(Property::canonicalize);
+        }
+
+        if let Some(attributes) = &mut self.attributes {
+            attributes.canonicalize();
+        }
+
+        self.source_map = None;
+    }
+}
+
+impl Cluster<'_> {
+    fn canonicalize(&mut self) {
+        self.nodes.0.compact();
+        self.edges.0.compact();
+        if let Some(attributes) = &mut self.attributes {
+            attributes.canonicalize();
+        }
+
+        self.clusters.sort_by_key(|c| c.id);
+        self.clusters.iter_mut().for_each(Cluster::canonicalize);
+    }
+}
+
+impl Property<'_> {
+    fn canonicalize(&mut self) {
+        let r#type = &self.r#type;
+        normalize(&mut self.node_default, |repr| Value::parse(r#type, repr));
+        normalize(&mut self.edge_default, |repr| Value::parse_for_edge(r#type, repr));
+
+        // stable, so that the first of duplicated values is kept, as it is the one read
+        self.nodes_property.sort_by_key(|p| p.id);
+        self.nodes_property.dedup_by_key(|p| p.id);
+        for node in &mut self.nodes_property {
+            normalize(&mut node.value, |repr| Value::parse(r#type, repr));
+        }
+        self.nodes_property.retain(|p| p.value != self.node_default);
+
+        self.edges_property.sort_by_key(|p| p.id);
+        self.edges_property.dedup_by_key(|p| p.id);
+        for edge in &mut self.edges_property {
+            normalize(&mut edge.value, |repr| Value::parse_for_edge(r#type, repr));
+        }
+        self.edges_property.retain(|p| p.value != self.edge_default);
+    }
+}
+
+impl Attributes<'_> {
+    fn canonicalize(&mut self) {
+        self.0.sort_by(|a, b| a.name.cmp(&b.name));
+        for attribute in &mut self.0 {
+            let r#type = &attribute.r#type;
+            normalize(&mut attribute.value, |repr| Value::parse(r#type, repr));
+        }
+    }
+}
+
+/// Write `repr` back from its decoded value, leaving the strings and the
+/// values that do not decode as they are.
+fn normalize(repr: &mut Cow<'_, str>, parse: impl for<'r> Fn(&'r str) -> Result<Value<'r>, String>) {
+    let normalized = match parse(repr) {
+        Ok(value) if value.property_type() != PropertyType::String => value.to_string(),
+        _ => return
+    };
+    if normalized != **repr {
+        *repr = Cow::Owned(normalized);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+
+    use super::format;
+
+    #[test]
+    fn test_same_content_same_output() {
+        let a = r#"(tlp "2.3"
+(nodes 0 1 2 3)
+(edge 1 1 2)
+(edge 0 0 1)
+(cluster 2
+(nodes 1 2)
+(edges 1)
+)
+(cluster 1
+(nodes 0 1 2)
+(edges 0 1)
+)
+(property  0 double "weight"
+(default "1.0" "0")
+(node 2 "2.50")
+(node 0 "1")
+(edge 1 "0.000")
+)
+(property  0 color "viewColor"
+(default "( 255, 0,0,255 )" "(0,0,0,0)")
+)
+(graph_attributes 0
+(string "name" "g")
+(double "scale" "1.50")
+)
+)"#;
+        let b = r#"(tlp "2.3"
+(nodes 0..3)
+(edge 0 0 1)
+(edge 1 1 2)
+(cluster 1
+(nodes 0..2)
+(edges 0 1)
+)
+(cluster 2
+(nodes 1 2)
+(edges 1)
+)
+(property  0 color "viewColor"
+(default "(255,0,0,255)" "(0,0,0,0)")
+)
+(property  0 double "weight"
+(default "1" "0")
+(node 2 "2.5")
+)
+(graph_attributes 0
+(double "scale" "1.5")
+(string "name" "g")
+)
+)"#;
+        let formatted = format(a).unwrap();
+        assert_eq!(formatted, format(b).unwrap());
+        assert_eq!(format(&formatted).unwrap(), formatted);
+
+        assert!(formatted.contains("(nodes 0..3)\n"));
+        assert!(formatted.contains("(property  0 double \"weight\"\n(default \"1\" \"0\")\n(node 2 \"2.5\")\n)\n"));
+        assert!(formatted.find("\"viewColor\"") < formatted.find("\"weight\""));
+        assert!(formatted.find("(double \"scale\" \"1.5\")") > formatted.find("(string \"name\" \"g\")"));
+
+        // the canonical form reads back as the same graph
+        let mut graph = Graph::parse_borrowed(a).unwrap();
+        graph.canonicalize();
+        assert_eq!(Graph::parse_borrowed(&formatted).unwrap(), graph);
+    }
+}
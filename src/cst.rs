@@ -0,0 +1,84 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::scan::Cursor;
+
+/// A node of the concrete syntax tree: where it is in the source, and what it holds.
+///
+/// Everything between the nodes (whitespaces, comments) is left in the
+/// source, which is how the tree stays lossless without storing it.
+#[derive(Debug)]
+pub(crate) struct CstNode {
+    pub(crate) span: Range<usize>,
+    kind: CstKind
+}
+
+#[derive(Debug)]
+enum CstKind {
+    Atom,
+    String,
+    List(Vec<CstNode>)
+}
+
+fn scan_cst(c: &mut Cursor) -> Option<CstNode> {
+    let start = c.pos();
+    let kind = if c.eat(b'(').is_some() {
+        let mut items = Vec::new();
+        loop {
+            c.ws0();
+            if c.eat(b')').is_some() {
+                break CstKind::List(items);
+            }
+            items.push(scan_cst(c)?);
+        }
+    } else if c.peek() == Some(b'"') {
+        c.string()?;
+        CstKind::String
+    } else {
+        c.atom()?;
+        CstKind::Atom
+    };
+    Some(CstNode { span: start..c.pos(), kind })
+}
+
+impl CstNode {
+    /// Read the whole file, which must be a single list apart from whitespaces and comments.
+    pub(crate) fn parse(source: &str) -> Result<CstNode, String> {
+        let mut cursor = Cursor::new(source);
+        let _ = cursor.eat_str("\u{feff}");
+        cursor.ws0();
+        scan_cst(&mut cursor)
+            .filter(|_| {
+                cursor.ws0();
+                cursor.rest().is_empty()
+            })
+            .ok_or_else(|| "The file is not a well formed list of blocks, its layout cannot be kept".to_string())
+    }
+
+    pub(crate) fn items(&self) -> &[CstNode] {
+        match &self.kind {
+            CstKind::List(items) => items,
+            _ => &[]
+        }
+    }
+
+    pub(crate) fn atom<'s>(&self, source: &'s str, index: usize) -> Option<&'s str> {
+        let item = self.items().get(index)?;
+        matches!(item.kind, CstKind::Atom).then(|| &source[item.span.clone()])
+    }
+
+    pub(crate) fn string<'s>(&self, source: &'s str, index: usize) -> Option<Cow<'s, str>> {
+        let item = self.items().get(index)?;
+        Cursor::new(&source[item.span.clone()]).string()
+    }
+
+    /// The lists among the items whose first atom is `tag`.
+    pub(crate) fn lists<'n>(&'n self, source: &'n str, tag: &'n str) -> impl DoubleEndedIterator<Item = &'n CstNode> {
+        self.items().iter()
+            .filter(move |item| item.atom(source, 0) == Some(tag))
+    }
+
+    pub(crate) fn id(&self, source: &str) -> Option<usize> {
+        self.atom(source, 1)?.parse().ok()
+    }
+}
@@ -0,0 +1,312 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use crate::cst::CstNode;
+use crate::writer::write_string;
+use crate::EdgeProperty;
+use crate::Edge;
+use crate::Graph;
+use crate::NodeProperty;
+use crate::ParseOptions;
+use crate::PropertyType;
+use crate::Value;
+
+/// Where to insert a line after the element ending at `pos`: at the end of
+/// its line when only a comment follows it there, or right after it otherwise.
+fn end_of_line(source: &str, pos: usize) -> usize {
+    let rest = &source[pos..];
+    let trailing = rest.find(['\r', '\n']).unwrap_or(rest.len());
+    let after = rest[..trailing].trim_start_matches([' ', '\t']);
+    if after.is_empty() || after.starts_with(';') {
+        pos + trailing
+    } else {
+        pos
+    }
+}
+
+/// A replacement of the source text, or an insertion when the range is empty.
+struct Splice {
+    range: Range<usize>,
+    key: SpliceKey,
+    text: String
+}
+
+/// What a splice stands for, so that editing the same thing again updates it.
+#[derive(PartialEq)]
+enum SpliceKey {
+    Replace,
+    /// A `(node ...)` or `(edge ...)` line added to the property starting at the given offset.
+    Value(usize, &'static str, usize),
+    Edge(usize)
+}
+
+/// A tlp file opened for editing, written back with only the edited lines changed.
+///
+/// The [Graph] is read as usual, and the source is kept along with its
+/// concrete syntax tree. The edits go through the document, which updates
+/// the graph and records the text to splice in the source: the whitespaces,
+/// comments, order and number formatting of everything else are untouched.
+pub struct Document<'a> {
+    source: &'a str,
+    root: CstNode,
+    graph: Graph<'a>,
+    splices: Vec<Splice>,
+    /// The line ending of the source, given to the inserted lines.
+    newline: &'static str
+}
+
+impl<'a> Document<'a> {
+    pub fn parse(source: &'a str, options: &ParseOptions) -> Result<Self, String> {
+        let graph = Graph::parse_with(source, options)?;
+
+        let root = CstNode::parse(source)?;
+
+        let newline = if source.contains("\r\n") { "\r\n" } else { "\n" };
+
+        Ok(Document { source, root, graph, splices: Vec::new(), newline })
+    }
+
+    /// The graph, with the edits applied.
+    pub fn graph(&self) -> &Graph<'a> {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> Graph<'a> {
+        self.graph
+    }
+
+    /// Set the value of `node` in the root property `name`.
+    pub fn set_node_value(&mut self, name: &str, node: usize, value: &Value) -> Result<(), String> {
+        if !self.graph.nodes.contains(node) {
+            return Err(format!("Unknown node {node}"));
+        }
+        self.set_value(name, "node", node, value)
+    }
+
+    /// Set the value of `edge` in the root property `name`.
+    pub fn set_edge_value(&mut self, name: &str, edge: usize, value: &Value) -> Result<(), String> {
+        if self.graph.edge(edge).is_none() {
+            return Err(format!("Unknown edge {edge}"));
+        }
+        self.set_value(name, "edge", edge, value)
+    }
+
+    fn set_value(&mut self, name: &str, tag: &'static str, id: usize, value: &Value) -> Result<(), String> {
+        let property = self.graph.properties.as_ref()
+            .and_then(|p| p.0.iter().find(|p| p.graph_id == 0 && p.name == name))
+            .ok_or_else(|| format!("No property {name:?}"))?;
+
+        // a layout holds positions for the nodes, but bends for the edges;
+        // a graph property holds graphs for the nodes, but sets of edges for the edges
+        let fits = match (&property.r#type, tag, value) {
+            (PropertyType::Layout, "node", Value::Coord(_)) => true,
+            (PropertyType::Layout, "edge", Value::Bends(_)) => true,
+            (PropertyType::Layout, _, _) => false,
+            (PropertyType::Graph, "node", Value::Graph(_)) => true,
+            (PropertyType::Graph, "edge", Value::EdgeSet(_)) => true,
+            (PropertyType::Graph, _, _) => false,
+            (r#type, _, value) => value.property_type() == *r#type,
+        };
+        if !fits {
+            return Err(format!("A {} value does not fit the {tag}s of the {} property {name:?}", value.property_type(), property.r#type));
+        }
+
+        let text = value.to_string();
+        let mut quoted = String::new();
+        write_string(&mut quoted, &text).map_err(|e| e.to_string())?;
+
+        // find what to splice before touching the graph, so that a failure leaves both untouched
+        let source = self.source;
+        let block = self.root.lists(source, "property")
+            .find(|p| p.id(source) == Some(0) && p.string(source, 3).as_deref() == Some(name))
+            .ok_or_else(|| format!("The property {name:?} is not in the source"))?;
+
+        let splice = match block.lists(source, tag).find(|l| l.id(source) == Some(id)) {
+            Some(line) => {
+                let range = line.items().get(2)
+                    .ok_or_else(|| format!("The {tag} {id} of {name:?} has no value"))?
+                    .span.clone();
+                (range, SpliceKey::Replace, quoted)
+            }
+            None => {
+                // after the lines of the same kind, the nodes being written before the edges
+                let anchor = block.lists(source, tag).next_back()
+                    .or_else(|| (tag == "edge").then(|| block.lists(source, "node").next_back()).flatten())
+                    .or_else(|| block.lists(source, "default").next_back())
+                    .or_else(|| block.items().last())
+                    .ok_or_else(|| format!("The property {name:?} is empty"))?
+                    .span.end;
+                let anchor = end_of_line(source, anchor);
+                let key = SpliceKey::Value(block.span.start, tag, id);
+                (anchor..anchor, key, format!("{}({tag} {id} {quoted})", self.newline))
+            }
+        };
+
+        let property = self.graph.properties.as_mut()
+            .and_then(|p| p.0.iter_mut().find(|p| p.graph_id == 0 && p.name == name))
+            .expect("the property was found above");
+        if tag == "node" {
+            match property.nodes_property.iter_mut().find(|p| p.id == id) {
+                Some(p) => p.value = Cow::Owned(text),
+                None => property.nodes_property.push(NodeProperty { id, value: Cow::Owned(text) })
+            }
+        } else {
+            match property.edges_property.iter_mut().find(|p| p.id == id) {
+                Some(p) => p.value = Cow::Owned(text),
+                None => property.edges_property.push(EdgeProperty { id, value: Cow::Owned(text) })
+            }
+        }
+
+        let (range, key, text) = splice;
+        self.splice(range, key, text);
+        Ok(())
+    }
+
+    /// Add an edge from `src` to `tgt`, numbered after the last one, and return its identifier.
+    pub fn add_edge(&mut self, src: usize, tgt: usize) -> Result<usize, String> {
+        for node in [src, tgt] {
+            if !self.graph.nodes.contains(node) {
+                return Err(format!("Unknown node {node}"));
+            }
+        }
+
+        let source = self.source;
+        let anchor = self.root.lists(source, "edge").next_back()
+            .or_else(|| self.root.lists(source, "nb_edges").next_back())
+            .or_else(|| self.root.lists(source, "nodes").next_back())
+            .ok_or("No nodes in the source")?
+            .span.end;
+        let anchor = end_of_line(source, anchor);
+        let count = self.root.lists(source, "nb_edges").next()
+            .and_then(|nb| nb.items().get(1))
+            .map(|count| count.span.clone());
+
+        let id = self.graph.edges.iter().map(|e| e.id + 1).max().unwrap_or(0);
+        self.graph.edges.0.push(Edge { id, src, tgt });
+
+        self.splice(anchor..anchor, SpliceKey::Edge(id), format!("{}(edge {id} {src} {tgt})", self.newline));
+        if let Some(count) = count {
+            self.splice(count, SpliceKey::Replace, self.graph.edges.len().to_string());
+        }
+        Ok(id)
+    }
+
+    fn splice(&mut self, range: Range<usize>, key: SpliceKey, text: String) {
+        match self.splices.iter_mut().find(|s| s.key == key && s.range == range) {
+            Some(splice) => splice.text = text,
+            None => self.splices.push(Splice { range, key, text })
+        }
+    }
+}
+
+/// Write the source with the edits spliced in.
+impl fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // stable: the insertions at the same place stay in the order they were made
+        let mut splices: Vec<&Splice> = self.splices.iter().collect();
+        splices.sort_by_key(|s| s.range.start);
+
+        let mut pos = 0;
+        for splice in splices {
+            f.write_str(&self.source[pos..splice.range.start])?;
+            f.write_str(&splice.text)?;
+            pos = splice.range.end;
+        }
+        f.write_str(&self.source[pos..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Coord;
+    use crate::ParseOptions;
+    use crate::Value;
+
+    use super::Document;
+
+    const SOURCE: &str = r#"; hand edited
+(tlp "2.3"
+(nb_nodes 3)
+(nodes 0..2)
+(nb_edges 1)
+(edge 0   0 1) ; the first edge
+(property  0 double "weight"
+(default "1.50" "0")
+(node 0 "2.50")  ; keep me
+(node 1 "3.0")
+)
+(property  0 layout "viewLayout"
+(default "(0,0,0)" "()")
+)
+)
+"#;
+
+    #[test]
+    fn test_untouched() {
+        let document = Document::parse(SOURCE, &ParseOptions::default()).unwrap();
+        assert_eq!(document.to_string(), SOURCE);
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut document = Document::parse(SOURCE, &ParseOptions::default()).unwrap();
+        document.set_node_value("weight", 1, &Value::Double(4.)).unwrap();
+        document.set_node_value("weight", 2, &Value::Double(0.5)).unwrap();
+        document.set_node_value("weight", 2, &Value::Double(0.25)).unwrap();
+        assert_eq!(document.add_edge(1, 2), Ok(1));
+        document.set_edge_value("viewLayout", 1, &Value::Bends(vec![Coord { x: 1., y: 2., z: 0. }])).unwrap();
+
+        assert_eq!(document.to_string(), r#"; hand edited
+(tlp "2.3"
+(nb_nodes 3)
+(nodes 0..2)
+(nb_edges 2)
+(edge 0   0 1) ; the first edge
+(edge 1 1 2)
+(property  0 double "weight"
+(default "1.50" "0")
+(node 0 "2.50")  ; keep me
+(node 1 "4")
+(node 2 "0.25")
+)
+(property  0 layout "viewLayout"
+(default "(0,0,0)" "()")
+(edge 1 "((1,2,0))")
+)
+)
+"#);
+
+        let graph = document.graph();
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.property("weight").unwrap().node_value(2), Ok(Value::Double(0.25)));
+        assert_eq!(graph.edge_polyline(1).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_insert_before_closing() {
+        let source = "(tlp \"2.3\"\n(nodes 0 1)\n(edge 0 0 1))";
+        let mut document = Document::parse(source, &ParseOptions::default()).unwrap();
+        document.add_edge(1, 0).unwrap();
+        assert_eq!(document.to_string(), "(tlp \"2.3\"\n(nodes 0 1)\n(edge 0 0 1)\n(edge 1 1 0))");
+    }
+
+    #[test]
+    fn test_windows_line_endings() {
+        let source = "\u{feff}(tlp \"2.3\"\r\n(nodes 0 1)\r\n(edge 0 0 1) ; first\r\n)\r\n";
+        let mut document = Document::parse(source, &ParseOptions::default()).unwrap();
+        document.add_edge(1, 0).unwrap();
+        assert_eq!(document.to_string(), "\u{feff}(tlp \"2.3\"\r\n(nodes 0 1)\r\n(edge 0 0 1) ; first\r\n(edge 1 1 0)\r\n)\r\n");
+    }
+
+    #[test]
+    fn test_invalid_edits() {
+        let mut document = Document::parse(SOURCE, &ParseOptions::default()).unwrap();
+        assert!(document.set_node_value("weight", 7, &Value::Double(1.)).is_err());
+        assert!(document.set_node_value("missing", 0, &Value::Double(1.)).is_err());
+        assert!(document.set_node_value("weight", 0, &Value::Int(1)).is_err());
+        assert!(document.set_edge_value("viewLayout", 0, &Value::Coord(Coord::default())).is_err());
+        assert!(document.add_edge(0, 9).is_err());
+        assert_eq!(document.to_string(), SOURCE);
+    }
+}
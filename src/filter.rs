@@ -0,0 +1,173 @@
+use crate::Edge;
+use crate::Graph;
+use crate::Ids;
+use crate::Property;
+use crate::Value;
+
+impl<'a> Graph<'a> {
+    /// The property `name` as the graph `graph_id` sees it, with the identifiers of its elements.
+    fn filtered_property(&self, graph_id: usize, name: &str) -> Result<(&Property<'a>, &Ids, Option<&Ids>), String> {
+        let (nodes, edges) = if graph_id == 0 {
+            (&*self.nodes, None)
+        } else {
+            let cluster = self.find_cluster(graph_id)
+                .ok_or_else(|| format!("Unknown cluster {graph_id}"))?;
+            (&**cluster.nodes(), Some(&**cluster.edges()))
+        };
+        let property = self.visible_property(graph_id, name)
+            .ok_or_else(|| format!("No property {name:?}"))?;
+        Ok((property, nodes, edges))
+    }
+
+    /// The nodes of the graph `graph_id` whose value, their own or the default one, satisfies `predicate`.
+    fn matching_nodes(&self, graph_id: usize, name: &str, mut predicate: impl FnMut(&Value) -> bool) -> Result<Ids, String> {
+        let (property, nodes, _) = self.filtered_property(graph_id, name)?;
+        let value_of = property.node_values();
+        let mut matching = Vec::new();
+        for node in nodes.iter() {
+            if predicate(&value_of(node)?) {
+                matching.push(node);
+            }
+        }
+        Ok(matching.into_iter().collect())
+    }
+
+    /// The edges of the graph `graph_id` whose value, their own or the default one, satisfies `predicate`.
+    fn matching_edges(&self, graph_id: usize, name: &str, mut predicate: impl FnMut(&Value) -> bool) -> Result<Vec<Edge>, String> {
+        let (property, _, edges) = self.filtered_property(graph_id, name)?;
+        let value_of = property.edge_values();
+        let mut matching = Vec::new();
+        for edge in self.edges.iter().filter(|e| edges.is_none_or(|edges| edges.contains(e.id))) {
+            if predicate(&value_of(edge.id)?) {
+                matching.push(edge.clone());
+            }
+        }
+        Ok(matching)
+    }
+
+    /// The graph of the nodes whose value of the property `name` satisfies
+    /// `predicate`, such as `|v| *v == Value::Bool(true)` for `viewSelection`,
+    /// and of the edges between them, see [Graph::induced_subgraph].
+    ///
+    /// The nodes without a value of their own are tested with the default one.
+    pub fn filter_nodes(&self, name: &str, predicate: impl FnMut(&Value) -> bool) -> Result<Graph<'a>, String> {
+        Ok(self.induced_subgraph(&self.matching_nodes(0, name, predicate)?))
+    }
+
+    /// The graph of the edges whose value of the property `name` satisfies
+    /// `predicate`, and of their ends; see [Graph::filter_nodes].
+    pub fn filter_edges(&self, name: &str, predicate: impl FnMut(&Value) -> bool) -> Result<Graph<'a>, String> {
+        let edges = self.matching_edges(0, name, predicate)?;
+        let nodes = edges.iter().flat_map(|e| [e.src, e.tgt]).collect();
+        Ok(self.restricted(nodes, edges))
+    }
+
+    /// Add to the graph `parent`, 0 being the root, a cluster of its nodes
+    /// matching as in [Graph::filter_nodes], and return its identifier.
+    /// The property is the one `parent` sees, see [Graph::visible_property].
+    pub fn filter_nodes_into_cluster(&mut self, parent: usize, name: &str, predicate: impl FnMut(&Value) -> bool) -> Result<usize, String> {
+        let nodes = self.matching_nodes(parent, name, predicate)?;
+        self.add_cluster(parent, nodes.iter())
+    }
+
+    /// Add to the graph `parent`, 0 being the root, a cluster of its edges
+    /// matching as in [Graph::filter_edges] and of their ends, and return its identifier.
+    pub fn filter_edges_into_cluster(&mut self, parent: usize, name: &str, predicate: impl FnMut(&Value) -> bool) -> Result<usize, String> {
+        let edges = self.matching_edges(parent, name, predicate)?;
+        let nodes = edges.iter().flat_map(|e| [e.src, e.tgt]).collect();
+        Ok(self.push_cluster(parent, nodes, edges.iter().map(|e| e.id).collect()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Color;
+    use crate::Graph;
+    use crate::Value;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..4)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(edge 3 3 4)
+(cluster 1
+(nodes 1..3)
+(edges 1 2)
+)
+(property  0 bool "viewSelection"
+(default "true" "false")
+(node 0 "false")
+(edge 2 "true")
+)
+(property  0 double "score"
+(default "0" "1")
+(node 1 "0.75")
+(node 3 "0.6")
+(edge 0 "0.2")
+)
+(property  1 double "score"
+(default "1" "0")
+)
+(property  0 color "viewColor"
+(default "(0,0,0,255)" "(0,0,0,255)")
+(node 2 "(255,0,0,255)")
+(node 4 "(0,0,255,255)")
+)
+)"#;
+
+    #[test]
+    fn test_filter_nodes() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+
+        // the nodes without a value of their own are selected by default
+        let selected = g.filter_nodes("viewSelection", |v| *v == Value::Bool(true)).unwrap();
+        assert_eq!(selected.nodes().to_vec(), [1, 2, 3, 4]);
+        assert_eq!(selected.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let scored = g.filter_nodes("score", |v| matches!(v, Value::Double(d) if *d > 0.5)).unwrap();
+        assert_eq!(scored.nodes().to_vec(), [1, 3]);
+        assert_eq!(scored.edges_iter().count(), 0);
+
+        let palette = [Color { r: 255, g: 0, b: 0, a: 255 }, Color { r: 0, g: 0, b: 255, a: 255 }];
+        let colored = g.filter_nodes("viewColor", |v| matches!(v, Value::Color(c) if palette.contains(c))).unwrap();
+        assert_eq!(colored.nodes().to_vec(), [2, 4]);
+        assert_eq!(colored.property("viewColor").unwrap().node_value(4), Ok(Value::Color(palette[1])));
+
+        assert!(g.filter_nodes("missing", |_| true).is_err());
+    }
+
+    #[test]
+    fn test_filter_edges() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        let selected = g.filter_edges("viewSelection", |v| *v == Value::Bool(true)).unwrap();
+        assert_eq!(selected.nodes().to_vec(), [2, 3]);
+        assert_eq!(selected.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [2]);
+
+        // only the matching edges, not all the edges between their ends
+        let heavy = g.filter_edges("score", |v| matches!(v, Value::Double(d) if *d > 0.5)).unwrap();
+        assert_eq!(heavy.nodes().to_vec(), [1, 2, 3, 4]);
+        assert_eq!(heavy.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_filter_into_cluster() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+
+        let root = g.filter_nodes_into_cluster(0, "score", |v| matches!(v, Value::Double(d) if *d > 0.5)).unwrap();
+        assert_eq!(g.find_cluster(root).unwrap().nodes().to_vec(), [1, 3]);
+
+        // the cluster sees its own score, 1 by default for the nodes and 0 for the edges
+        let nested = g.filter_nodes_into_cluster(1, "score", |v| *v == Value::Double(1.)).unwrap();
+        let nested = g.find_cluster(nested).unwrap();
+        assert_eq!(nested.parent().unwrap().id(), 1);
+        assert_eq!((nested.nodes().to_vec(), nested.edges().to_vec()), (vec![1, 2, 3], vec![1, 2]));
+
+        let edges = g.filter_edges_into_cluster(1, "viewSelection", |v| *v == Value::Bool(true)).unwrap();
+        let edges = g.find_cluster(edges).unwrap();
+        assert_eq!((edges.nodes().to_vec(), edges.edges().to_vec()), (vec![2, 3], vec![2]));
+
+        assert!(g.filter_nodes_into_cluster(9, "score", |_| true).is_err());
+        assert_eq!(Graph::parse_borrowed(&g.to_string()).unwrap(), g);
+    }
+}
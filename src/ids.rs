@@ -0,0 +1,580 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Copied;
+use std::iter::FusedIterator;
+use std::ops::RangeInclusive;
+use std::slice;
+
+/// Consecutive identifiers written `start..end` in a tlp file (both ends included).
+#[derive(PartialEq, Debug, Clone)]
+pub struct IdsRange(pub(crate) RangeInclusive<usize>);
+
+/// Identifiers written one by one in a tlp file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct IdsList(pub(crate) Vec<usize>);
+
+impl IdsRange {
+    /// `None` for a range of more identifiers than a `usize` can count,
+    /// which only `0..=usize::MAX` is.
+    pub(crate) fn new(range: RangeInclusive<usize>) -> Option<Self> {
+        if range.start() <= range.end() {
+            (range.end() - range.start()).checked_add(1)?;
+        }
+        Some(IdsRange(range))
+    }
+
+    pub fn iter(&self) -> RangeInclusive<usize> {
+        self.0.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            // cannot overflow, IdsRange::new rejects the range of every usize
+            self.last() - self.first() + 1
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.start() > self.0.end()
+    }
+
+    pub fn first(&self) -> usize {
+        *self.0.start()
+    }
+
+    pub fn last(&self) -> usize {
+        *self.0.end()
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.0.contains(&id)
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+}
+
+impl IdsList {
+    pub fn iter(&self) -> Copied<slice::Iter<'_, usize>> {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Only meaningful once the list is sorted, which `Ids` guarantees.
+    pub fn contains(&self, id: usize) -> bool {
+        self.0.binary_search(&id).is_ok()
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.0.clone()
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum IdsBloc {
+    Range(IdsRange),
+    List(IdsList)
+}
+
+impl IdsBloc {
+    pub fn iter(&self) -> IdsBlocIter<'_> {
+        match self {
+            IdsBloc::Range(ids_range) => IdsBlocIter::Range(ids_range.iter()),
+            IdsBloc::List(ids_list) => IdsBlocIter::List(ids_list.iter()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Range(r) => r.len(),
+            Self::List(l) => l.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Range(r) => r.is_empty(),
+            Self::List(l) => l.is_empty()
+        }
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        match self {
+            Self::Range(r) => r.contains(id),
+            Self::List(l) => l.contains(id)
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        match self {
+            Self::Range(r) => r.to_vec(),
+            Self::List(l) => l.to_vec()
+        }
+    }
+
+    fn first(&self) -> Option<usize> {
+        match self {
+            Self::Range(r) => (!r.is_empty()).then(|| r.first()),
+            Self::List(l) => l.0.first().copied()
+        }
+    }
+
+    fn last(&self) -> Option<usize> {
+        match self {
+            Self::Range(r) => (!r.is_empty()).then(|| r.last()),
+            Self::List(l) => l.0.last().copied()
+        }
+    }
+
+    fn is_sorted(&self) -> bool {
+        match self {
+            Self::Range(_) => true,
+            Self::List(l) => l.0.windows(2).all(|w| w[0] < w[1])
+        }
+    }
+}
+
+/// Iterator over the identifiers of a single [IdsBloc].
+#[derive(Debug, Clone)]
+pub enum IdsBlocIter<'ids> {
+    Range(RangeInclusive<usize>),
+    List(Copied<slice::Iter<'ids, usize>>)
+}
+
+impl Iterator for IdsBlocIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Range(r) => r.next(),
+            Self::List(l) => l.next()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Range(r) => r.size_hint(),
+            Self::List(l) => l.size_hint()
+        }
+    }
+}
+
+impl DoubleEndedIterator for IdsBlocIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Range(r) => r.next_back(),
+            Self::List(l) => l.next_back()
+        }
+    }
+}
+
+impl ExactSizeIterator for IdsBlocIter<'_> {}
+impl FusedIterator for IdsBlocIter<'_> {}
+
+/// Ordered set of node or edge identifiers.
+///
+/// The blocs are kept sorted and disjoint, so that the identifiers are
+/// always iterated in increasing order and membership is a binary search.
+#[derive(Debug, Clone, Default)]
+pub struct Ids {
+    blocs: Vec<IdsBloc>,
+    len: usize
+}
+
+impl Ids {
+    /// Build the set from blocs in any order, as they may appear in a file.
+    /// Blocs that are already sorted and disjoint are kept untouched;
+    /// otherwise all the identifiers are sorted, deduplicated and compacted.
+    ///
+    /// Fails when the blocs hold more identifiers than a `usize` can count.
+    pub fn new(blocs: Vec<IdsBloc>) -> Result<Self, String> {
+        let blocs: Vec<IdsBloc> = blocs.into_iter()
+            .filter(|b| !b.is_empty())
+            .collect();
+
+        let ordered = blocs.iter().all(IdsBloc::is_sorted)
+            && blocs.windows(2).all(|w| w[0].last() < w[1].first());
+
+        if !ordered {
+            return Ok(blocs.iter()
+                .flat_map(IdsBloc::iter)
+                .collect());
+        }
+
+        let len = blocs.iter().map(IdsBloc::len).try_fold(0, usize::checked_add)
+            .ok_or("More identifiers than a usize can count")?;
+        Ok(Ids { blocs, len })
+    }
+
+    /// Rewrite the blocs with as few of them as possible: runs of at least
+    /// [MIN_RANGE_LEN] consecutive identifiers become ranges, everything
+    /// in between is gathered in lists.
+    pub fn compact(&mut self) {
+        *self = Self::from_sorted(self.iter());
+    }
+
+    pub fn union(&self, other: &Ids) -> Ids {
+        let mut builder = IdsBuilder::default();
+        let mut a = self.iter().peekable();
+        let mut b = other.iter().peekable();
+        loop {
+            let id = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => match x.cmp(y) {
+                    Ordering::Less => a.next(),
+                    Ordering::Greater => b.next(),
+                    Ordering::Equal => {
+                        b.next();
+                        a.next()
+                    }
+                },
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (None, None) => break
+            };
+            builder.push(id.unwrap());
+        }
+        builder.build()
+    }
+
+    pub fn intersection(&self, other: &Ids) -> Ids {
+        let (small, large) = if self.len <= other.len { (self, other) } else { (other, self) };
+        Self::from_sorted(small.iter().filter(|id| large.contains(*id)))
+    }
+
+    pub fn difference(&self, other: &Ids) -> Ids {
+        Self::from_sorted(self.iter().filter(|id| !other.contains(*id)))
+    }
+
+    pub fn is_subset(&self, other: &Ids) -> bool {
+        self.len <= other.len && self.iter().all(|id| other.contains(id))
+    }
+
+    /// Build the set from identifiers that are known to be strictly increasing.
+    fn from_sorted(ids: impl Iterator<Item=usize>) -> Ids {
+        let mut builder = IdsBuilder::default();
+        ids.for_each(|id| builder.push(id));
+        builder.build()
+    }
+
+    pub fn blocs(&self) -> &[IdsBloc] {
+        &self.blocs
+    }
+
+    pub fn iter(&self) -> IdsIter<'_> {
+        IdsIter {
+            blocs: self.blocs.iter(),
+            front: None,
+            back: None,
+            remaining: self.len
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        let idx = self.blocs.partition_point(|b| b.last() < Some(id));
+        self.blocs.get(idx)
+            .is_some_and(|b| b.contains(id))
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.blocs.first().and_then(IdsBloc::first)
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.blocs.last().and_then(IdsBloc::last)
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+}
+
+/// Shortest run of consecutive identifiers written as a range by [Ids::compact]:
+/// below that, `4 5` is shorter than `4..5`.
+pub const MIN_RANGE_LEN: usize = 3;
+
+/// Accumulates strictly increasing identifiers into compacted blocs.
+#[derive(Default)]
+struct IdsBuilder {
+    blocs: Vec<IdsBloc>,
+    pending: Vec<usize>,
+    len: usize
+}
+
+impl IdsBuilder {
+    fn push(&mut self, id: usize) {
+        if let Some(&last) = self.pending.last()
+            && last + 1 != id {
+            self.flush_run();
+        }
+        self.pending.push(id);
+        self.len += 1;
+    }
+
+    /// Move the trailing run of consecutive identifiers out of `pending`,
+    /// either as a range when long enough or into the current list.
+    fn flush_run(&mut self) {
+        if self.pending.len() >= MIN_RANGE_LEN {
+            let run = IdsRange(self.pending[0]..=self.pending[self.pending.len() - 1]);
+            self.blocs.push(IdsBloc::Range(run));
+        } else {
+            match self.blocs.last_mut() {
+                Some(IdsBloc::List(list)) => list.0.append(&mut self.pending),
+                _ => self.blocs.push(IdsBloc::List(IdsList(std::mem::take(&mut self.pending))))
+            }
+        }
+        self.pending.clear();
+    }
+
+    fn build(mut self) -> Ids {
+        if !self.pending.is_empty() {
+            self.flush_run();
+        }
+        Ids { blocs: self.blocs, len: self.len }
+    }
+}
+
+impl FromIterator<usize> for Ids {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut ids: Vec<usize> = iter.into_iter().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        Self::from_sorted(ids.into_iter())
+    }
+}
+
+/// Panics on `0..=usize::MAX`, which holds more identifiers than a `usize` can count.
+impl From<RangeInclusive<usize>> for Ids {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        let range = IdsRange::new(range).expect("More identifiers than a usize can count");
+        Ids::new(vec![IdsBloc::Range(range)]).unwrap()
+    }
+}
+
+impl fmt::Display for IdsRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.first(), self.last())
+    }
+}
+
+impl fmt::Display for IdsList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_separated(f, self.0.iter())
+    }
+}
+
+impl fmt::Display for IdsBloc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Range(r) => r.fmt(f),
+            Self::List(l) => l.fmt(f)
+        }
+    }
+}
+
+/// Space separated blocs, as they are written after `nodes` or `edges`.
+impl fmt::Display for Ids {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_separated(f, self.blocs.iter())
+    }
+}
+
+fn write_separated<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: impl Iterator<Item=T>) -> fmt::Result {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        item.fmt(f)?;
+    }
+    Ok(())
+}
+
+impl PartialEq for Ids {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<'ids> IntoIterator for &'ids Ids {
+    type Item = usize;
+    type IntoIter = IdsIter<'ids>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the identifiers of [Ids], in increasing order.
+#[derive(Debug, Clone)]
+pub struct IdsIter<'ids> {
+    blocs: slice::Iter<'ids, IdsBloc>,
+    front: Option<IdsBlocIter<'ids>>,
+    back: Option<IdsBlocIter<'ids>>,
+    remaining: usize
+}
+
+impl Iterator for IdsIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(id) = self.front.as_mut().and_then(Iterator::next) {
+                self.remaining -= 1;
+                return Some(id);
+            }
+
+            match self.blocs.next() {
+                Some(bloc) => self.front = Some(bloc.iter()),
+                None => {
+                    let id = self.back.as_mut().and_then(Iterator::next);
+                    if id.is_some() {
+                        self.remaining -= 1;
+                    }
+                    return id;
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for IdsIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(id) = self.back.as_mut().and_then(DoubleEndedIterator::next_back) {
+                self.remaining -= 1;
+                return Some(id);
+            }
+
+            match self.blocs.next_back() {
+                Some(bloc) => self.back = Some(bloc.iter()),
+                None => {
+                    let id = self.front.as_mut().and_then(DoubleEndedIterator::next_back);
+                    if id.is_some() {
+                        self.remaining -= 1;
+                    }
+                    return id;
+                }
+            }
+        }
+    }
+}
+
+impl ExactSizeIterator for IdsIter<'_> {}
+impl FusedIterator for IdsIter<'_> {}
+
+#[cfg(test)]
+mod test {
+    use super::{Ids, IdsBloc, IdsList, IdsRange};
+
+    fn mixed() -> Ids {
+        Ids::new(vec![
+            IdsBloc::List(IdsList(vec![0, 2])),
+            IdsBloc::Range(IdsRange(5..=8)),
+            IdsBloc::List(IdsList(vec![10]))
+        ]).unwrap()
+    }
+
+    #[test]
+    fn test_len_and_contains() {
+        let ids = mixed();
+        assert_eq!(ids.len(), 7);
+        assert_eq!(IdsRange(3..=1_000_000).len(), 999_998);
+        for id in [0, 2, 5, 6, 8, 10] {
+            assert!(ids.contains(id), "{id}");
+        }
+        for id in [1, 3, 4, 9, 11, 100] {
+            assert!(!ids.contains(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_ranges_up_to_usize_max() {
+        let last = IdsRange::new(usize::MAX..=usize::MAX).unwrap();
+        assert_eq!(last.len(), 1);
+        assert_eq!(IdsRange::new(1..=usize::MAX).unwrap().len(), usize::MAX);
+        assert_eq!(IdsRange::new(0..=usize::MAX), None);
+
+        // each range can be counted, but not all the identifiers together
+        let halves = vec![
+            IdsBloc::Range(IdsRange::new(0..=usize::MAX / 2).unwrap()),
+            IdsBloc::Range(IdsRange::new(usize::MAX / 2 + 1..=usize::MAX).unwrap()),
+        ];
+        assert!(Ids::new(halves).is_err());
+
+        let ids = Ids::new(vec![IdsBloc::List(IdsList(vec![0])), IdsBloc::Range(last)]).unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids.iter().rev().collect::<Vec<_>>(), vec![usize::MAX, 0]);
+    }
+
+    #[test]
+    fn test_double_ended() {
+        let ids = mixed();
+        let mut iter = ids.iter();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next_back(), Some(10));
+        assert_eq!(iter.next_back(), Some(8));
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 5, 6, 7]);
+        assert_eq!(ids.iter().rev().collect::<Vec<_>>(), vec![10, 8, 7, 6, 5, 2, 0]);
+    }
+
+    #[test]
+    fn test_unordered_blocs_are_sorted() {
+        let ids = Ids::new(vec![
+            IdsBloc::List(IdsList(vec![9, 3])),
+            IdsBloc::Range(IdsRange(2..=4)),
+        ]).unwrap();
+        assert_eq!(ids.to_vec(), vec![2, 3, 4, 9]);
+        assert!(ids.contains(9));
+        assert_eq!(ids, Ids::new(vec![IdsBloc::List(IdsList(vec![2, 3, 4, 9]))]).unwrap());
+    }
+
+    #[test]
+    fn test_compaction() {
+        let ids: Ids = [10, 0, 1, 2, 3, 4, 7, 8, 12, 13, 14, 20, 3].into_iter().collect();
+        assert_eq!(ids.to_string(), "0..4 7 8 10 12..14 20");
+        assert_eq!(ids.blocs().len(), 4);
+
+        let mut ids = Ids::new(vec![IdsBloc::List(IdsList(vec![0, 1, 2, 3]))]).unwrap();
+        assert_eq!(ids.to_string(), "0 1 2 3");
+        ids.compact();
+        assert_eq!(ids.to_string(), "0..3");
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = Ids::from(0..=9);
+        let b: Ids = [5, 6, 7, 8, 9, 10, 11, 20].into_iter().collect();
+
+        assert_eq!(a.union(&b).to_string(), "0..11 20");
+        assert_eq!(a.intersection(&b).to_string(), "5..9");
+        assert_eq!(a.difference(&b).to_string(), "0..4");
+        assert_eq!(b.difference(&a).to_string(), "10 11 20");
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!b.is_subset(&a));
+        assert!(Ids::default().is_subset(&a));
+        assert_eq!(a.union(&b).len(), 13);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::Cluster;
+use crate::Clusters;
+use crate::Edge;
+use crate::Edges;
+use crate::EdgesIds;
+use crate::Graph;
+use crate::Ids;
+use crate::NodesIds;
+use crate::Properties;
+
+impl<'a> Cluster<'a> {
+    /// The cluster with only the given elements, none if none of its nodes is left.
+    fn restricted(&self, nodes: &Ids, edges: &Ids) -> Option<Cluster<'a>> {
+        let kept = self.nodes.intersection(nodes);
+        if kept.is_empty() {
+            return None;
+        }
+        Some(Cluster {
+            id: self.id,
+            nodes: NodesIds(kept),
+            edges: EdgesIds(self.edges.intersection(edges)),
+            attributes: self.attributes.clone(),
+            clusters: self.clusters.iter().filter_map(|c| c.restricted(nodes, edges)).collect()
+        })
+    }
+}
+
+impl<'a> Graph<'a> {
+    /// The graph of the given nodes and of the edges between them.
+    ///
+    /// The identifiers, the property values of the elements left and the
+    /// attributes are kept. The clusters keep the nodes and edges left, and
+    /// the ones with no node left are dropped along with their properties.
+    pub fn induced_subgraph(&self, nodes: &Ids) -> Graph<'a> {
+        let nodes = self.nodes.intersection(nodes);
+        let edges = self.edges.iter()
+            .filter(|e| nodes.contains(e.src) && nodes.contains(e.tgt))
+            .cloned()
+            .collect();
+        self.restricted(nodes, edges)
+    }
+
+    /// The graph of the given elements, the ends of the edges being among the nodes,
+    /// with the properties and the clusters trimmed as [Graph::induced_subgraph] does.
+    pub(crate) fn restricted(&self, nodes: Ids, edges: Vec<Edge>) -> Graph<'a> {
+        let edge_ids: Ids = edges.iter().map(|e| e.id).collect();
+
+        let clusters: Vec<Cluster<'a>> = self.clusters.iter()
+            .flat_map(|c| &c.0)
+            .filter_map(|c| c.restricted(&nodes, &edge_ids))
+            .collect();
+        let mut kept_graphs = vec![0];
+        let mut stack: Vec<&Cluster> = clusters.iter().collect();
+        while let Some(cluster) = stack.pop() {
+            kept_graphs.push(cluster.id);
+            stack.extend(&cluster.clusters);
+        }
+
+        let properties: Vec<_> = self.properties.iter()
+            .flat_map(|p| &p.0)
+            .filter(|p| kept_graphs.contains(&p.graph_id))
+            .map(|p| {
+                let mut property = p.clone();
+                property.retain_elements(&nodes, &edge_ids);
+                property
+            })
+            .collect();
+
+        Graph {
+            version: self.version,
+            author: self.author.clone(),
+            comments: self.comments.clone(),
+            date: self.date.clone(),
+            nodes: NodesIds(nodes),
+            edges: Edges(edges),
+            properties: (!properties.is_empty()).then_some(Properties(properties)),
+            attributes: self.attributes.clone(),
+            clusters: (!clusters.is_empty()).then_some(Clusters(clusters)),
+            unknown: Vec::new(),
+            source_map: None
+        }
+    }
+
+    /// The graph of the nodes at most `k` edges away from `center`, whatever
+    /// the direction of the edges, as [Graph::induced_subgraph] builds it.
+    pub fn ego_network(&self, center: usize, k: usize) -> Result<Graph<'a>, String> {
+        if !self.nodes.contains(center) {
+            return Err(format!("Unknown node {center}"));
+        }
+
+        let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+        for edge in self.edges.iter() {
+            neighbors.entry(edge.src).or_default().push(edge.tgt);
+            neighbors.entry(edge.tgt).or_default().push(edge.src);
+        }
+
+        let mut distances = HashMap::from([(center, 0)]);
+        let mut queue = VecDeque::from([center]);
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[&node];
+            if distance == k {
+                continue;
+            }
+            for &neighbor in neighbors.get(&node).into_iter().flatten() {
+                if let Entry::Vacant(entry) = distances.entry(neighbor) {
+                    entry.insert(distance + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        Ok(self.induced_subgraph(&distances.into_keys().collect()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+    use crate::Ids;
+    use crate::Value;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..5)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 3 2)
+(edge 3 3 4)
+(edge 4 4 5)
+(edge 5 0 5)
+(cluster 1
+(nodes 0 1 2)
+(edges 0 1)
+(cluster 2
+(nodes 2)
+(edges )
+)
+)
+(cluster 3
+(nodes 4 5)
+(edges 4)
+)
+(property  0 string "viewLabel"
+(default "" "")
+(node 1 "b")
+(node 4 "e")
+(edge 1 "x")
+(edge 4 "y")
+)
+(property  3 int "local"
+(default "0" "0")
+)
+(graph_attributes 0
+(string "name" "ring")
+)
+)"#;
+
+    #[test]
+    fn test_induced_subgraph() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        let sub = g.induced_subgraph(&[0, 1, 3, 9].into_iter().collect());
+
+        assert_eq!(sub.nodes().to_vec(), [0, 1, 3]);
+        assert_eq!(sub.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [0]);
+        // the clusters keep what is left, those left empty are dropped
+        let clusters: Vec<_> = sub.clusters_pre_order().map(|c| (c.id(), c.nodes().to_vec(), c.edges().to_vec())).collect();
+        assert_eq!(clusters, [(1, vec![0, 1], vec![0])]);
+        assert!(sub.properties.as_ref().unwrap().0.iter().all(|p| p.graph_id == 0));
+
+        let label = sub.property("viewLabel").unwrap();
+        assert_eq!(label.node_value(1), Ok(Value::String("b".into())));
+        assert_eq!(label.nodes_property.len(), 1);
+        assert!(label.edges_property.is_empty());
+
+        assert_eq!(Graph::parse_borrowed(&sub.to_string()).unwrap(), sub);
+        assert!(g.induced_subgraph(&Ids::default()).nodes().is_empty());
+    }
+
+    #[test]
+    fn test_ego_network() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+
+        // the edges are followed both ways: 2 reaches 3 through the edge 3 -> 2
+        let ego = g.ego_network(2, 1).unwrap();
+        assert_eq!(ego.nodes().to_vec(), [1, 2, 3]);
+        assert_eq!(ego.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2]);
+
+        assert_eq!(g.ego_network(2, 2).unwrap().nodes().to_vec(), [0, 1, 2, 3, 4]);
+        assert_eq!(g.ego_network(2, 0).unwrap().nodes().to_vec(), [2]);
+        let whole = g.ego_network(0, 3).unwrap();
+        assert_eq!(whole.nodes().len(), 6);
+        assert_eq!(whole.property("local").map(|p| p.graph_id), Some(3));
+        assert!(g.ego_network(6, 1).is_err());
+    }
+}
@@ -1,159 +1,86 @@
 #[cfg(feature = "petgraph")]
 pub mod petgraph;
-
+#[cfg(feature = "mmap")]
+mod mmap;
+mod canonical;
+mod cst;
+mod document;
+mod filter;
+mod ids;
+mod induced;
+mod load;
+mod metanode;
+mod quotient;
+mod scan;
+mod selection;
+mod sexpr;
+mod span;
+mod subgraph;
+mod value;
+mod version;
+pub mod visual;
+mod writer;
+
+pub use canonical::format;
+pub use document::Document;
+pub use ids::Ids;
+pub use ids::IdsBloc;
+pub use ids::IdsBlocIter;
+pub use ids::IdsIter;
+pub use ids::IdsList;
+pub use ids::IdsRange;
+pub use quotient::QUOTIENT_CLUSTER;
+pub use quotient::QUOTIENT_COUNT;
+pub use quotient::QUOTIENT_DENSITY;
+pub use quotient::QUOTIENT_WEIGHT;
+pub use sexpr::SExpr;
+pub use sexpr::UnknownBlock;
+pub use span::Location;
+pub use span::PropertySpans;
+pub use span::SourceMap;
+pub use span::Span;
+pub use subgraph::PostOrder;
+pub use subgraph::PreOrder;
+pub use subgraph::SubgraphRef;
+pub use value::Color;
+pub use value::Coord;
+pub use value::Size;
+pub use value::Value;
+pub use version::TlpVersion;
+pub use writer::WriteOptions;
+#[cfg(feature = "mmap")]
+pub use mmap::MappedTlp;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
-use winnow::ascii::alpha1;
 use winnow::ascii::dec_uint;
-use winnow::ascii::multispace0;
-use winnow::ascii::multispace1;
-use winnow::ascii::space0;
-use winnow::ascii::space1;
-use winnow::ascii::take_escaped;
 use winnow::combinator::alt;
+use winnow::combinator::cut_err;
 use winnow::combinator::delimited;
-use winnow::combinator::not;
+use winnow::combinator::fail;
 use winnow::combinator::opt;
 use winnow::combinator::repeat;
 use winnow::combinator::separated;
 use winnow::combinator::terminated;
-use winnow::error::ParserError;
+use winnow::error::ContextError;
+use winnow::error::ErrMode;
+use winnow::error::StrContext;
+use winnow::error::StrContextValue;
 use winnow::prelude::*;
-use winnow::stream::AsChar;
-use winnow::stream::Compare;
-use winnow::stream::Stream;
-use winnow::stream::StreamIsPartial;
-use winnow::token::take_until;
-use winnow::token::take_while;
 use winnow::Parser;
 use winnow::Result;
 
-#[derive(PartialEq, Debug, Clone)]
-pub struct IdsRange(RangeInclusive<usize>);
-#[derive(PartialEq, Debug, Clone)]
-pub struct IdsList(Vec<usize>);
-
-
-impl IdsRange {
-    pub fn iter(&self) -> impl Iterator<Item=usize> {
-        self.0.clone().into_iter()
-    }
-}
-
-impl IdsList {
-    pub fn iter(&self) -> impl Iterator<Item=usize> {
-        self.0.iter().cloned()
-    }
-}
-
-#[derive(PartialEq, Debug, Clone)]
-pub enum IdsBloc {
-    Range(IdsRange),
-    List(IdsList)
-}
-
-impl IdsBloc {
-    pub fn iter(&self) -> Box<dyn Iterator<Item=usize> +'_>  {
-        match self {
-            IdsBloc::Range(ids_range) => Box::new(ids_range.iter()),
-            IdsBloc::List(ids_list) => Box::new(ids_list.iter()),
-        }
-    }
-}
-
-#[derive(PartialEq, Debug, Clone)]
-pub struct Ids(Vec<IdsBloc>);
-
-impl  Ids {
-
-    fn iter<'ids>(&'ids self) -> IdsIter<'ids>{
-        IdsIter {
-            full: Box::new(self.0.iter()),
-            current_bloc: None
-        }
-    }
-}
-
-struct IdsIter<'ids> {
-    full: Box<dyn Iterator<Item=&'ids IdsBloc> +'ids >,
-    current_bloc: Option<Box<dyn Iterator<Item=usize> +'ids>>
-}
-
-impl<'ids> Iterator for IdsIter<'ids> {
-    type Item = usize;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if  self.current_bloc.is_none() {
-            let iter = self.full.next()
-                .map(|b| b.iter());
-            self.current_bloc = iter;
-        }
-
-        if let Some(bloc) = &mut self.current_bloc {
-            bloc.next()
-        } else {
-            None
-        }
-    }
-}
-
-
-impl IdsRange {
-    pub fn len(&self) -> usize {
-        self.0.clone().count()
-    }
-
-    pub fn to_vec(&self) -> Vec<usize> {
-        self.iter().collect()
-    }
-}
-
-impl IdsList {
-    pub fn len(&self) -> usize {
-        self.0.len()
-    }
-
-    pub fn to_vec(&self) -> Vec<usize> {
-        self.iter().collect()
-    }
-}
-
-impl IdsBloc {
-    pub fn len(&self) -> usize {
-        match self {
-            Self::Range(r) => r.len(),
-            Self::List(l) => l.len()
-
-        }
-    }
-
-    // it would be better to use an iterator
-    #[cfg(test)]
-    pub fn to_vec(&self) -> Vec<usize> {
-        match self {
-            Self::Range(r) => r.to_vec(),
-            Self::List(l) => l.to_vec()
-        }
-    }
-}
-
-impl Ids {
-    pub fn len(&self) -> usize {
-        self.0.iter().map(IdsBloc::len).sum()
-    }
-
-    #[cfg(test)]
-    // it would be better to use an iterator
-    pub fn to_vec(&self) -> Vec<usize> {
-        self.0.iter()
-            .map(|bloc| bloc.to_vec())
-            .flatten()
-            .collect()
-    }
-}
+use crate::scan::scan;
+use crate::scan::scan_repeat;
+use crate::scan::Cursor;
+use crate::sexpr::scan_sexpr;
+use crate::sexpr::Section;
+use crate::span::SpanRecorder;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct NodesIds(Ids);
@@ -174,7 +101,7 @@ impl Deref for EdgesIds {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Edge {
     pub id: usize,
     pub src: usize,
@@ -192,14 +119,30 @@ impl Deref for Edges {
     }
 }
 
-#[derive(PartialEq, Debug)]
-pub struct Date(String);
+#[derive(PartialEq, Debug, Clone)]
+pub struct Date<'a>(Cow<'a, str>);
 
-#[derive(PartialEq, Debug)]
-pub struct Comments(String);
+#[derive(PartialEq, Debug, Clone)]
+pub struct Comments<'a>(Cow<'a, str>);
 
-#[derive(PartialEq, Debug)]
-pub struct Author(String);
+#[derive(PartialEq, Debug, Clone)]
+pub struct Author<'a>(Cow<'a, str>);
+
+/// Name of the attribute holding the name of a graph.
+pub const NAME: &str = "name";
+
+/// Name of the property holding the positions of the nodes and the bends of the edges.
+pub const VIEW_LAYOUT: &str = "viewLayout";
+
+/// Name of the property mapping the metanodes to their clusters and the meta-edges to their edges.
+pub const VIEW_META_GRAPH: &str = "viewMetaGraph";
+
+/// Name of the attribute of a collapsed cluster holding the edges of the graph
+/// it was nested in between its nodes that are not its own, see [Graph::collapse].
+pub const META_INNER_EDGES: &str = "metaInnerEdges";
+
+/// Name of the property marking the selected nodes and edges.
+pub const VIEW_SELECTION: &str = "viewSelection";
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum PropertyType {
@@ -210,246 +153,600 @@ pub enum PropertyType {
     Int,
     Layout,
     String,
-    Size
+    Size,
+    BoolVector,
+    ColorVector,
+    DoubleVector,
+    IntVector,
+    CoordVector,
+    SizeVector,
+    StringVector
 }
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct Property {
+pub struct Property<'a> {
     graph_id: usize,
-    name: String,
+    name: Cow<'a, str>,
     r#type: PropertyType,
-    node_default: String,
-    edge_default: String,
+    node_default: Cow<'a, str>,
+    edge_default: Cow<'a, str>,
 
-    nodes_property: Vec<NodeProperty>
+    nodes_property: Vec<NodeProperty<'a>>,
+    edges_property: Vec<EdgeProperty<'a>>
 }
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct Attribute {
+pub struct Attribute<'a> {
     r#type: PropertyType,
-    name: String,
-    value: String
+    name: Cow<'a, str>,
+    value: Cow<'a, str>
 }
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct Attributes(Vec<Attribute>);
+pub struct Attributes<'a>(Vec<Attribute<'a>>);
 #[derive(PartialEq, Debug, Clone)]
-pub struct Properties(Vec<Property>);
+pub struct Properties<'a>(Vec<Property<'a>>);
 
 
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct NodeProperty{
+pub struct NodeProperty<'a>{
     id: usize,
-    value: String
+    value: Cow<'a, str>
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub struct EdgeProperty<'a>{
+    id: usize,
+    value: Cow<'a, str>
+}
 
-#[derive(PartialEq, Debug)]
-struct Cluster {
+
+#[derive(PartialEq, Debug, Clone)]
+struct Cluster<'a> {
     id: usize,
     nodes: NodesIds,
     edges: EdgesIds,
+    attributes: Option<Attributes<'a>>,
 
-    clusters: Vec<Cluster>
+    clusters: Vec<Cluster<'a>>
 }
 
 #[derive(PartialEq, Debug)]
-pub struct Clusters(Vec<Cluster>);
+pub struct Clusters<'a>(Vec<Cluster<'a>>);
 
-#[derive(PartialEq, Debug)]
-pub struct Graph {
-    version: String,
+/// A graph read from a tlp file.
+///
+/// The strings (property values, labels, attributes...) borrow from the
+/// parsed text when it is kept alive, see [Graph::parse_borrowed];
+/// [Graph::into_owned] detaches them from it.
+#[derive(Debug)]
+pub struct Graph<'a> {
+    version: TlpVersion,
 
-    author: Option<Author>,
-    comments: Option<Comments>,
-    date: Option<Date>,
+    author: Option<Author<'a>>,
+    comments: Option<Comments<'a>>,
+    date: Option<Date<'a>>,
 
     nodes: NodesIds,
     edges: Edges,
 
-    properties: Option<Properties>,
-    attributes: Option<Attributes>,
+    properties: Option<Properties<'a>>,
+    attributes: Option<Attributes<'a>>,
+
+    clusters: Option<Clusters<'a>>,
+
+    /// Blocks kept as they were read in lenient mode, written back where they were.
+    unknown: Vec<UnknownBlock<'a>>,
+
+    source_map: Option<SourceMap>,
+}
 
-    clusters: Option<Clusters>,
+/// The source map only tells where the graph was read from: two graphs read
+/// with and without [ParseOptions::spans] are equal.
+impl PartialEq for Graph<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.author == other.author
+            && self.comments == other.comments
+            && self.date == other.date
+            && self.nodes == other.nodes
+            && self.edges == other.edges
+            && self.properties == other.properties
+            && self.attributes == other.attributes
+            && self.clusters == other.clusters
+            && self.unknown == other.unknown
+    }
 }
 
-impl Graph {
-    pub fn nodes_iter(&self) -> impl Iterator<Item=usize> {
+/// How to read a tlp file, see [Graph::parse_with].
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Keep the top-level blocks that are not part of the format, such as
+    /// the `controller` of Tulip projects, instead of failing on them.
+    pub lenient: bool,
+    /// Record where each element was read, see [Graph::source_map].
+    pub spans: bool,
+    /// Fail on files that are not valid UTF-8 in [Graph::load_with],
+    /// instead of reading their invalid bytes as Windows-1252.
+    pub strict_encoding: bool
+}
+
+impl<'a> Graph<'a> {
+    /// Parse a tlp file without copying its strings: they point into `input`.
+    pub fn parse_borrowed(input: &'a str) -> Result<Self, String> {
+        Self::parse_with(input, &ParseOptions::default())
+    }
+
+    /// Parse a tlp file as [Graph::parse_borrowed] does, following `options`.
+    pub fn parse_with(input: &'a str, options: &ParseOptions) -> Result<Self, String> {
+        let mut spans = SpanRecorder::new(input, options.spans);
+        let mut graph = (|i: &mut &'a str| graph(i, options, &mut spans)).parse(input)
+            .map_err(|e| e.to_string())?;
+        graph.source_map = spans.finish();
+        Ok(graph)
+    }
+
+    pub fn into_owned(self) -> Graph<'static> {
+        Graph {
+            version: self.version,
+            author: self.author.map(|Author(a)| Author(owned(a))),
+            comments: self.comments.map(|Comments(c)| Comments(owned(c))),
+            date: self.date.map(|Date(d)| Date(owned(d))),
+            nodes: self.nodes,
+            edges: self.edges,
+            properties: self.properties.map(Properties::into_owned),
+            attributes: self.attributes.map(Attributes::into_owned),
+            clusters: self.clusters.map(Clusters::into_owned),
+            unknown: self.unknown.into_iter().map(UnknownBlock::into_owned).collect(),
+            source_map: self.source_map,
+        }
+    }
+
+    pub fn version(&self) -> TlpVersion {
+        self.version
+    }
+
+    /// Where the elements were read, when parsed with [ParseOptions::spans].
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+
+    /// The blocks kept without being understood, see [ParseOptions::lenient].
+    pub fn unknown_blocks(&self) -> &[UnknownBlock<'a>] {
+        &self.unknown
+    }
+
+    pub fn nodes_iter(&self) -> IdsIter<'_> {
         self.nodes.iter()
     }
 
+    pub fn nodes(&self) -> &NodesIds {
+        &self.nodes
+    }
+
     pub fn edges_iter(&self) -> impl Iterator<Item=&Edge> {
         self.edges.iter()
     }
+
+    pub fn property(&self, name: &str) -> Option<&Property<'a>> {
+        self.properties.as_ref()?
+            .0.iter()
+            .find(|p| p.name == name)
+    }
+
+    /// The property `name` of the root graph, added with the `(node, edge)`
+    /// default values if missing; an error if it has another type.
+    pub(crate) fn root_property_mut(&mut self, name: &'static str, r#type: PropertyType, defaults: (&'static str, &'static str)) -> Result<&mut Property<'a>, String> {
+        let properties = &mut self.properties.get_or_insert_with(|| Properties(Vec::new())).0;
+        let index = match properties.iter().position(|p| p.graph_id == 0 && p.name == name) {
+            Some(index) if properties[index].r#type != r#type => {
+                return Err(format!("The property {name:?} is not a {type} property"));
+            }
+            Some(index) => index,
+            None => {
+                properties.push(Property {
+                    graph_id: 0,
+                    name: Cow::Borrowed(name),
+                    r#type,
+                    node_default: Cow::Borrowed(defaults.0),
+                    edge_default: Cow::Borrowed(defaults.1),
+                    nodes_property: Vec::new(),
+                    edges_property: Vec::new()
+                });
+                properties.len() - 1
+            }
+        };
+        Ok(&mut properties[index])
+    }
+
+    pub fn edge(&self, edge_id: usize) -> Option<&Edge> {
+        // the edges are usually numbered by their position in the file
+        self.edges.get(edge_id)
+            .filter(|e| e.id == edge_id)
+            .or_else(|| self.edges.iter().find(|e| e.id == edge_id))
+    }
+
+    /// The points to draw `edge_id` through, from `viewLayout`: the position
+    /// of its source, its bends, then the position of its target.
+    pub fn edge_polyline(&self, edge_id: usize) -> Result<Vec<Coord>, String> {
+        let edge = self.edge(edge_id)
+            .ok_or_else(|| format!("Unknown edge {edge_id}"))?;
+        let layout = self.property(VIEW_LAYOUT)
+            .ok_or_else(|| format!("No {VIEW_LAYOUT} property"))?;
+
+        let position = |node| match layout.node_value(node)? {
+            Value::Coord(coord) => Ok(coord),
+            value => Err(format!("{VIEW_LAYOUT} holds a {} value", value.property_type()))
+        };
+        let bends = match layout.edge_value(edge_id)? {
+            Value::Bends(bends) => bends,
+            value => return Err(format!("{VIEW_LAYOUT} holds a {} value", value.property_type()))
+        };
+
+        let mut polyline = Vec::with_capacity(bends.len() + 2);
+        polyline.push(position(edge.src)?);
+        polyline.extend(bends);
+        polyline.push(position(edge.tgt)?);
+        Ok(polyline)
+    }
 }
 
 
-impl FromStr for Graph {
+impl FromStr for Graph<'static> {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        graph.parse(s)
-            .map_err(|e| e.to_string())
+        Graph::parse_borrowed(s)
+            .map(Graph::into_owned)
     }
 }
 
+/// The values by identifier, the first one winning as in [Property::node_value].
+fn index_values<'p>(values: impl Iterator<Item=(usize, &'p str)>) -> HashMap<usize, &'p str> {
+    let mut index = HashMap::new();
+    for (id, repr) in values {
+        index.entry(id).or_insert(repr);
+    }
+    index
+}
 
-fn parse_tag<Input, Content, Error, Inner>(tag: &'static str, mut f: Inner) -> impl Parser<Input, Content, Error>
-where 
-Input: Stream + StreamIsPartial + Compare<char> + for<'a> Compare<&'a str>,
-Inner: Parser<Input, Content, Error>,
-Error: ParserError<Input> + Debug, 
-<Input as Stream>::Token: AsChar,
-Content: Debug
-{
-    move |input: &mut Input| {
+fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
 
-        let _ = (('(', space0, tag, space1).parse_next(input))?; 
-        let res = (f.parse_next(input))?; 
-        let _ = ((space0, opt(')')).parse_next(input))?;
-        Ok(res)
+impl Clusters<'_> {
+    pub fn into_owned(self) -> Clusters<'static> {
+        Clusters(self.0.into_iter().map(Cluster::into_owned).collect())
     }
 }
 
+impl<'a> Cluster<'a> {
+    fn into_owned(self) -> Cluster<'static> {
+        Cluster {
+            id: self.id,
+            nodes: self.nodes,
+            edges: self.edges,
+            attributes: self.attributes.map(Attributes::into_owned),
+            clusters: self.clusters.into_iter().map(Cluster::into_owned).collect()
+        }
+    }
 
+    /// The `name` attribute of the cluster.
+    fn name(&self) -> Option<&str> {
+        self.attributes.as_ref()?
+            .0.iter()
+            .find(|a| a.name == NAME)
+            .map(|a| &*a.value)
+    }
 
-fn parse_comma(input: &mut &str) -> ModalResult<()> {
-    (space0, ',', space0)
-        .value(())
-        .parse_next(input)
+    /// The cluster `id`, among this one and its descendants.
+    fn find_mut(&mut self, id: usize) -> Option<&mut Cluster<'a>> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.clusters.iter_mut().find_map(|c| c.find_mut(id))
+    }
+
+    /// Call `f` on this cluster and on its descendants.
+    fn for_each_mut(&mut self, f: &mut impl FnMut(&mut Cluster<'a>)) {
+        f(self);
+        for cluster in &mut self.clusters {
+            cluster.for_each_mut(f);
+        }
+    }
 }
 
-fn parse_ids_range(input: &mut &str) -> ModalResult<IdsRange> {
-    let start = dec_uint(input)?;
-    "..".parse_next(input)?;
-    let end = dec_uint(input)?;
+impl Properties<'_> {
+    pub fn into_owned(self) -> Properties<'static> {
+        Properties(self.0.into_iter().map(Property::into_owned).collect())
+    }
+}
+
+impl Property<'_> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    Ok(IdsRange(RangeInclusive::new(start, end)))
+    pub fn property_type(&self) -> &PropertyType {
+        &self.r#type
+    }
+
+    /// The value of `node`, or the default one when the node has none of its own.
+    pub fn node_value(&self, node: usize) -> Result<Value<'_>, String> {
+        let repr = self.nodes_property.iter()
+            .find(|p| p.id == node)
+            .map_or(&self.node_default, |p| &p.value);
+        Value::parse(&self.r#type, repr)
+    }
+
+    /// The value of `edge`, or the default one when the edge has none of its own.
+    /// The edge values of a layout are its bends, see [Value::parse_for_edge].
+    pub fn edge_value(&self, edge: usize) -> Result<Value<'_>, String> {
+        let repr = self.edges_property.iter()
+            .find(|p| p.id == edge)
+            .map_or(&self.edge_default, |p| &p.value);
+        Value::parse_for_edge(&self.r#type, repr)
+    }
+
+    /// [Property::node_value] for many nodes, their values being indexed once.
+    pub(crate) fn node_values<'p>(&'p self) -> impl Fn(usize) -> Result<Value<'p>, String> + 'p {
+        let reprs = index_values(self.nodes_property.iter().map(|p| (p.id, &*p.value)));
+        move |node| Value::parse(&self.r#type, reprs.get(&node).copied().unwrap_or(&self.node_default))
+    }
+
+    /// [Property::edge_value] for many edges, their values being indexed once.
+    pub(crate) fn edge_values<'p>(&'p self) -> impl Fn(usize) -> Result<Value<'p>, String> + 'p {
+        let reprs = index_values(self.edges_property.iter().map(|p| (p.id, &*p.value)));
+        move |edge| Value::parse_for_edge(&self.r#type, reprs.get(&edge).copied().unwrap_or(&self.edge_default))
+    }
+
+    /// Drop the values of the nodes and edges that are not in `nodes` and `edges`.
+    fn retain_elements(&mut self, nodes: &Ids, edges: &Ids) {
+        self.nodes_property.retain(|p| nodes.contains(p.id));
+        self.edges_property.retain(|p| edges.contains(p.id));
+    }
+
+    pub fn into_owned(self) -> Property<'static> {
+        Property {
+            graph_id: self.graph_id,
+            name: owned(self.name),
+            r#type: self.r#type,
+            node_default: owned(self.node_default),
+            edge_default: owned(self.edge_default),
+            nodes_property: self.nodes_property.into_iter()
+                .map(|p| NodeProperty { id: p.id, value: owned(p.value) })
+                .collect(),
+            edges_property: self.edges_property.into_iter()
+                .map(|p| EdgeProperty { id: p.id, value: owned(p.value) })
+                .collect()
+        }
+    }
 }
 
-fn parse_ids_list(input: &mut &str) -> ModalResult<IdsList> {
-    separated(1.., terminated( dec_uint::<_, usize, _>, not("..")), multispace1)
-        .map(|l| IdsList(l))
-        .parse_next(input)
+impl Attributes<'_> {
+    pub fn into_owned(self) -> Attributes<'static> {
+        Attributes(self.0.into_iter().map(Attribute::into_owned).collect())
+    }
 }
 
-fn parse_ids_bloc(input: &mut &str) -> ModalResult<IdsBloc> {
-    alt((
-        parse_ids_range.map(|r| IdsBloc::Range(r)),
-        parse_ids_list.map(|l| IdsBloc::List(l))
-    )).parse_next(input)
+impl Attribute<'_> {
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            r#type: self.r#type,
+            name: owned(self.name),
+            value: owned(self.value)
+        }
+    }
 }
 
-fn parse_ids(input: &mut &str) -> ModalResult<Ids> {
-    separated(1.., parse_ids_bloc, multispace1)
-        .map(|ids| Ids(ids))
-        .parse_next(input)
+
+/// Skip the whitespaces and `;` comments, allowed between any two tokens.
+fn ws0(input: &mut &str) -> ModalResult<()> {
+    scan(input, |c| {
+        c.ws0();
+        Some(())
+    })
 }
 
-fn nodes_ids(input: &mut &str) -> ModalResult<NodesIds> {
-    parse_tag("nodes", parse_ids)
-    .map(|ids| NodesIds(ids))
+fn ws1(input: &mut &str) -> ModalResult<()> {
+    scan(input, Cursor::ws1)
+}
+
+fn parse_tag<'a, Content, Inner>(tag: &'static str, mut f: Inner) -> impl Parser<&'a str, Content, ErrMode<ContextError>>
+where
+Inner: Parser<&'a str, Content, ErrMode<ContextError>>
+{
+    move |input: &mut &'a str| {
+        scan(input, |c| c.open_tag(tag))?;
+        let res = (f.parse_next(input))?;
+        scan(input, |c| {
+            c.close_tag();
+            Some(())
+        })?;
+        Ok(res)
+    }
+}
+
+
+
+fn parse_ids_range(input: &mut &str) -> ModalResult<IdsRange> {
+    scan(input, |c| {
+        let start = c.usize()?;
+        c.eat_str("..")?;
+        let end = c.usize()?;
+        IdsRange::new(RangeInclusive::new(start, end))
+    })
+}
+
+/// Parse a list of identifiers, with room for `capacity` of them.
+fn parse_ids_list(input: &mut &str, capacity: usize) -> ModalResult<IdsList> {
+    scan(input, |c| {
+        let mut ids = Vec::with_capacity(capacity);
+        loop {
+            // stop before an identifier starting a range, or anything that is not an identifier
+            let mut lookahead = Cursor::new(c.rest());
+            if !ids.is_empty() && lookahead.ws0() == 0 {
+                break;
+            }
+            match lookahead.usize() {
+                Some(id) if lookahead.eat_str("..").is_none() => ids.push(id),
+                _ => break
+            }
+            *c = lookahead;
+        }
+        (!ids.is_empty()).then_some(IdsList(ids))
+    })
+}
+
+fn parse_ids_bloc(input: &mut &str, capacity: usize) -> ModalResult<IdsBloc> {
+    // look after the first identifier to choose the kind of bloc without backtracking
+    let mut lookahead = Cursor::new(input);
+    let is_range = lookahead.usize().is_some() && lookahead.eat_str("..").is_some();
+
+    if is_range {
+        cut_err(parse_ids_range.map(IdsBloc::Range)).parse_next(input)
+    } else {
+        parse_ids_list(input, capacity).map(IdsBloc::List)
+    }
+}
+
+/// Parse identifiers, `capacity` being how many a list of them is expected to hold.
+fn parse_ids(input: &mut &str, version: TlpVersion, capacity: usize) -> ModalResult<Ids> {
+    let blocs: Vec<IdsBloc> = separated(1.., |i: &mut &str| parse_ids_bloc(i, capacity), ws1).parse_next(input)?;
+    if !version.has_ranges() && blocs.iter().any(|b| matches!(b, IdsBloc::Range(_))) {
+        return cut_expected(input, "identifiers listed one by one before tlp 2.2");
+    }
+    // too many identifiers for a usize to count: fail instead of leaving them out
+    Ids::new(blocs).map_err(|_| ErrMode::Cut(ContextError::new()))
+}
+
+fn nodes_ids(input: &mut &str, version: TlpVersion, capacity: usize) -> ModalResult<NodesIds> {
+    // a cluster may have no nodes nor edges: `(nodes )`
+    parse_tag("nodes", opt(|i: &mut &str| parse_ids(i, version, capacity)))
+    .map(|ids| NodesIds(ids.unwrap_or_default()))
     .parse_next(input)
 }
 
-fn edges_ids(input: &mut &str) -> ModalResult<EdgesIds> {
-    parse_tag("edges", parse_ids)
-    .map(|ids| EdgesIds(ids))
+fn edges_ids(input: &mut &str, version: TlpVersion) -> ModalResult<EdgesIds> {
+    parse_tag("edges", opt(|i: &mut &str| parse_ids(i, version, 0)))
+    .map(|ids| EdgesIds(ids.unwrap_or_default()))
     .parse_next(input)
 }
 
 
-fn cluster(input: &mut &str) -> ModalResult<Cluster> {
+fn cluster<'a>(input: &mut &'a str, version: TlpVersion, spans: &mut SpanRecorder) -> ModalResult<Cluster<'a>> {
 
-    fn cluster_inner(input: &mut &str) -> ModalResult<Cluster> {
-        let id: usize = terminated(dec_uint, multispace1).parse_next(input)?;
-        let nodes = terminated(nodes_ids, multispace1).parse_next(input)?;
-        let edges = terminated(edges_ids, multispace0).parse_next(input)?;
+    fn cluster_inner<'a>(input: &mut &'a str, version: TlpVersion, spans: &mut SpanRecorder) -> ModalResult<Cluster<'a>> {
+        let id: usize = terminated(dec_uint, ws1).parse_next(input)?;
+        // up to tlp 2.0, the name follows the identifier; it is kept as the name attribute of the cluster
+        let before = input.len();
+        let name = opt(parse_string).parse_next(input)?;
+        if name.is_some() {
+            if !version.has_inline_cluster_names() {
+                return cut_expected(input, "a cluster named in its graph_attributes after tlp 2.0");
+            }
+            spans.attribute(id, NAME, before, input.len());
+            ws1.parse_next(input)?;
+        }
+        let nodes = terminated(|i: &mut &str| nodes_ids(i, version, 0), ws1).parse_next(input)?;
+        let edges = terminated(|i: &mut &str| edges_ids(i, version), ws0).parse_next(input)?;
 
-        let clusters = repeat(.., cluster).parse_next(input)?;
+        let clusters = repeat(.., terminated(|i: &mut &'a str| cluster(i, version, spans), ws0)).parse_next(input)?;
 
-        let _ = multispace0.parse_next(input)?;
-        Ok(Cluster { id, nodes, edges, clusters})
+        ws0.parse_next(input)?;
+        let attributes = name.map(|name| Attributes(vec![
+            Attribute { r#type: PropertyType::String, name: Cow::Borrowed(NAME), value: name }
+        ]));
+        Ok(Cluster { id, nodes, edges, attributes, clusters})
     }
 
-    parse_tag("cluster", cluster_inner)
-        .parse_next(input)
+    let checkpoint = spans.checkpoint();
+    let before = input.len();
+    let cluster = parse_tag("cluster", |i: &mut &'a str| cluster_inner(i, version, spans))
+        .parse_next(input);
+    let cluster = cluster.inspect_err(|_| spans.rollback(checkpoint))?;
+    spans.cluster(cluster.id, before, input.len());
+    Ok(cluster)
 }
 
-fn clusters(input: &mut &str) -> ModalResult<Clusters> {
-    separated(1.., cluster, multispace1)
-        .map(|c| Clusters(c))
+fn clusters<'a>(input: &mut &'a str, version: TlpVersion, spans: &mut SpanRecorder) -> ModalResult<Clusters<'a>> {
+    separated(1.., |i: &mut &'a str| cluster(i, version, spans), ws1)
+        .map(Clusters)
         .parse_next(input)
 }
 
-fn nodes_amount_and_ids(input: &mut &str) -> ModalResult<NodesIds> {
-    let nb_nodes: Option<usize> = opt(terminated(parse_tag("nb_nodes", dec_uint), multispace1)).parse_next(input)?;
-
-    // TODO handle it to improve reading
-
-    let _ = terminated(opt((';', take_until(.., '\n'))), multispace0).parse_next(input)?;
+fn nodes_amount_and_ids(input: &mut &str, version: TlpVersion) -> ModalResult<NodesIds> {
+    let nb_nodes: Option<usize> = opt(terminated(parse_tag("nb_nodes", dec_uint), ws1)).parse_next(input)?;
 
-    let nodes = nodes_ids.parse_next(input)?;
+    // before tlp 2.2, the nodes are a single list of the announced length;
+    // after, they are mostly written as ranges, which need no room.
+    // The amount is only a hint: do not trust it beyond what the input can hold
+    let capacity = if version.has_ranges() {
+        0
+    } else {
+        nb_nodes.unwrap_or(0).min(input.len() / "0 ".len())
+    };
+    let nodes = nodes_ids(input, version, capacity)?;
 
-    if let Some(nb_nodes) = nb_nodes {
-        if nodes.len() != nb_nodes {
-            eprintln!("[WARNING] Expected {nb_nodes} but obtained {}", nodes.len());
-        }
+    if let Some(nb_nodes) = nb_nodes
+        && nodes.len() != nb_nodes {
+        eprintln!("[WARNING] Expected {nb_nodes} but obtained {}", nodes.len());
     }
     Ok(nodes)
 }
 
-fn parse_string(input: &mut &str) -> ModalResult<String> {
-    alt(("\"\"".map(|_| "".to_owned()),
-        delimited(
-        '"', 
-        take_escaped(alt((alpha1, space1, take_while(1.., |c| c!= '"'))),
-         '\\', 
-         '"'), 
-         '"'
-        )
-        .map(|s: &str| s.to_owned())
-    )).parse_next(input)
+/// Fail without backtracking, telling what was `expected` instead.
+fn cut_expected<O>(input: &mut &str, expected: &'static str) -> ModalResult<O> {
+    cut_err(fail)
+        .context(StrContext::Expected(StrContextValue::Description(expected)))
+        .parse_next(input)
+}
+
+fn parse_string<'a>(input: &mut &'a str) -> ModalResult<Cow<'a, str>> {
+    scan(input, Cursor::string)
 }
 
-fn date(input: &mut &str) -> ModalResult<Date> {
+fn date<'a>(input: &mut &'a str) -> ModalResult<Date<'a>> {
     parse_tag("date", parse_string)
         .map(Date)
         .parse_next(input)
 }
 
-fn comments(input: &mut &str) -> ModalResult<Comments> {
+fn comments<'a>(input: &mut &'a str) -> ModalResult<Comments<'a>> {
     parse_tag("comments", parse_string)
         .map(Comments)
         .parse_next(input)
 }
 
-fn author(input: &mut &str) -> ModalResult<Author> {
+fn author<'a>(input: &mut &'a str) -> ModalResult<Author<'a>> {
     parse_tag("author", parse_string)
         .map(Author)
         .parse_next(input)
 }
 
+#[cfg(test)]
 fn edge(input: &mut &str) -> ModalResult<Edge> {
+    scan(input, scan_edge)
+}
 
-    fn edge_inner(input: &mut &str) -> ModalResult<(usize, usize, usize)> {
-        let id = dec_uint.parse_next(input)?;
-        space1.parse_next(input)?;
-        let src = dec_uint.parse_next(input)?;
-        space1.parse_next(input)?;
-        let tgt = dec_uint.parse_next(input)?;
-
-        Ok((id, src, tgt))
+fn scan_edge(c: &mut Cursor) -> Option<Edge> {
+    // the lines as Tulip writes them, `(edge 0 1 2)`, are read in one go
+    let mut line = c.clone();
+    if line.eat_str("(edge ").is_some()
+        && let Some([id, src, tgt]) = line.short_usizes(b')') {
+        *c = line;
+        return Some(Edge { id, src, tgt });
     }
 
-    parse_tag("edge", edge_inner)
-        .map(|(id, src, tgt)| {
-            Edge{
-                id, src, tgt
-            }
-        })
-        .parse_next(input)
+    c.open_tag("edge")?;
+    let id = c.usize()?;
+    c.ws1()?;
+    let src = c.usize()?;
+    c.ws1()?;
+    let tgt = c.usize()?;
+    c.close_tag();
+    Some(Edge { id, src, tgt })
 }
 
 fn nb_edges(input: &mut &str) -> ModalResult<usize> {
@@ -458,28 +755,30 @@ fn nb_edges(input: &mut &str) -> ModalResult<usize> {
 }
 
 
-fn edges(input: &mut &str) -> ModalResult<Edges> {
-    let count = opt(delimited(multispace0, nb_edges, multispace0))
+fn edges(input: &mut &str, spans: &mut SpanRecorder) -> ModalResult<Edges> {
+    let count = opt(delimited(ws0, nb_edges, ws0))
         .parse_next(input)?;
 
-    let _ = terminated(opt((';', take_until(.., '\n'))), multispace0).parse_next(input)?;
-
-/*
-    // XXX for an unknwon reason the number of edges may not work
-    let edges = if let Some(count) = count {
-        dbg!("Try to parse nb edges: ", count);
-        separated::<_, Edge, Vec<Edge>, _,_,_,_>(count, edge, multispace0).parse_next(input)?
-    } else {
-       dbg!("Unknow amount of edges");
-        separated(.., edge, multispace0).parse_next(input)?
-    };
-*/
-    let edges: Vec<Edge> = separated(.., edge, multispace0).parse_next(input)?;
-
-    if let Some(count) = count {
-        if edges.len() != count {
-            eprintln!("[WARNING] {count} edges expected, but {} obtained.", edges.len());
-        }
+    // the announced amount is only a hint: do not trust it beyond what the input can hold
+    let shortest_edge = "(edge 0 0 0)".len();
+    let capacity = count.unwrap_or(0).min(input.len() / shortest_edge);
+
+    // a malformed line stops the loop, and the error is reported from there by the following parsers
+    let edges = scan(input, |c| {
+        let mut edges = Vec::with_capacity(capacity);
+        scan_repeat(c, |line| {
+            let before = line.rest().len();
+            let edge = scan_edge(line)?;
+            spans.edge(edge.id, before, line.rest().len());
+            edges.push(edge);
+            Some(())
+        });
+        Some(edges)
+    })?;
+
+    if let Some(count) = count
+        && edges.len() != count {
+        eprintln!("[WARNING] {count} edges expected, but {} obtained.", edges.len());
     }
     
     Ok(Edges(edges))
@@ -495,13 +794,20 @@ fn property_type(input: &mut &str) -> ModalResult<PropertyType> {
         "graph".value(PropertyType::Graph),
         "bool".value(PropertyType::Bool),
         "size".value(PropertyType::Size),
+        "vector<bool>".value(PropertyType::BoolVector),
+        "vector<color>".value(PropertyType::ColorVector),
+        "vector<double>".value(PropertyType::DoubleVector),
+        "vector<int>".value(PropertyType::IntVector),
+        "vector<coord>".value(PropertyType::CoordVector),
+        "vector<size>".value(PropertyType::SizeVector),
+        "vector<string>".value(PropertyType::StringVector),
     )).parse_next(input)
 }
 
-fn property_default(input: &mut &str) -> ModalResult<(String, String)>  {
-    fn default_inner(input: &mut &str) -> ModalResult<(String, String)> {
-        let node = terminated(parse_string, multispace1).parse_next(input)?;
-        let edge = terminated(parse_string, multispace0).parse_next(input)?;
+fn property_default<'a>(input: &mut &'a str) -> ModalResult<(Cow<'a, str>, Cow<'a, str>)>  {
+    fn default_inner<'a>(input: &mut &'a str) -> ModalResult<(Cow<'a, str>, Cow<'a, str>)> {
+        let node = terminated(parse_string, ws1).parse_next(input)?;
+        let edge = terminated(parse_string, ws0).parse_next(input)?;
 
         Ok((node, edge))
     }
@@ -509,87 +815,248 @@ fn property_default(input: &mut &str) -> ModalResult<(String, String)>  {
     parse_tag("default", default_inner).parse_next(input)
 }
 
-fn property_for_node(input: &mut &str) -> ModalResult<NodeProperty> {
-    fn for_node_inner(input: &mut &str) -> ModalResult<NodeProperty> {
-        let id: usize = terminated(dec_uint, multispace1).parse_next(input)?;
-        let value: String = terminated(parse_string, multispace0).parse_next(input)?;
-        Ok(NodeProperty {
-            id, value
-        })
-    }
-
-    parse_tag("node", for_node_inner)
-        .parse_next(input)
+/// Parse `(<tag> <id> "<value>")`, shared by the node and edge values of a property.
+fn scan_property_value<'a>(c: &mut Cursor<'a>, tag: &str) -> Option<(usize, Cow<'a, str>)> {
+    c.open_tag(tag)?;
+    let id = c.usize()?;
+    c.ws0();
+    let value = c.string()?;
+    c.ws0();
+    c.close_tag();
+    Some((id, value))
 }
 
+#[cfg(test)]
+fn property_for_node<'a>(input: &mut &'a str) -> ModalResult<NodeProperty<'a>> {
+    scan(input, |c| {
+        let (id, value) = scan_property_value(c, "node")?;
+        Some(NodeProperty { id, value })
+    })
+}
 
-fn property(input: &mut &str) -> ModalResult<Property> {
-    fn property_inner(input: &mut &str) -> ModalResult<Property> {
-        let graph_id: usize = delimited(multispace0, dec_uint, multispace1).parse_next(input)?;
-        let r#type = terminated(property_type, multispace1).parse_next(input)?;
-        let name = terminated(parse_string, multispace1).parse_next(input)?;
-
-        let default = terminated(property_default, multispace1).parse_next(input)?;
 
-        let nodes_property: Option<Vec<NodeProperty>> = opt(terminated(repeat(.., terminated(property_for_node, multispace0)), multispace0))
-            .parse_next(input)?;
-        Ok(Property { graph_id, name, r#type, node_default: default.0, edge_default: default.1, nodes_property: nodes_property.unwrap_or_default() })
+/// Parse a property of a graph of `nb_nodes` nodes.
+fn property<'a>(input: &mut &'a str, nb_nodes: usize, spans: &mut SpanRecorder) -> ModalResult<Property<'a>> {
+    fn property_inner<'a>(input: &mut &'a str, nb_nodes: usize, spans: &mut SpanRecorder) -> ModalResult<Property<'a>> {
+        let graph_id: usize = delimited(ws0, dec_uint, ws1).parse_next(input)?;
+        let r#type = terminated(property_type, ws1).parse_next(input)?;
+        let name = terminated(parse_string, ws1).parse_next(input)?;
+
+        let default = terminated(property_default, ws1).parse_next(input)?;
+
+        // room for a value per node, if it has any, given back once read;
+        // do not trust the amount beyond what the input can hold
+        let capacity = nb_nodes.min(input.len() / r#"(node 0 "")"#.len());
+        let mut nodes_property = Vec::new();
+        let mut edges_property = Vec::new();
+        scan(input, |c| {
+            scan_repeat(c, |line| {
+                let before = line.rest().len();
+                let mut node_line = line.clone();
+                if let Some((id, value)) = scan_property_value(&mut node_line, "node") {
+                    *line = node_line;
+                    spans.property_value(true, id, before, line.rest().len());
+                    if nodes_property.is_empty() {
+                        nodes_property.reserve(capacity);
+                    }
+                    nodes_property.push(NodeProperty { id, value });
+                } else {
+                    let (id, value) = scan_property_value(line, "edge")?;
+                    spans.property_value(false, id, before, line.rest().len());
+                    edges_property.push(EdgeProperty { id, value });
+                }
+                Some(())
+            });
+            Some(())
+        })?;
+        nodes_property.shrink_to_fit();
+
+        Ok(Property { graph_id, name, r#type, node_default: default.0, edge_default: default.1, nodes_property, edges_property })
     }
 
-    parse_tag("property", property_inner).parse_next(input)
+    let checkpoint = spans.checkpoint();
+    let before = input.len();
+    let property = parse_tag("property", |i: &mut &'a str| property_inner(i, nb_nodes, spans))
+        .parse_next(input);
+    let property = property.inspect_err(|_| spans.rollback(checkpoint))?;
+    spans.property(property.graph_id, &property.name, before, input.len());
+    Ok(property)
 }
 
-fn properties(input: &mut &str) -> ModalResult<Properties> {
-    repeat(.., terminated(property, multispace0))
+fn properties<'a>(input: &mut &'a str, nb_nodes: usize, spans: &mut SpanRecorder) -> ModalResult<Properties<'a>> {
+    repeat(.., terminated(|i: &mut &'a str| property(i, nb_nodes, spans), ws0))
         .map(Properties)
         .parse_next(input)
 }
 
-fn attribute(input: &mut &str) -> ModalResult<Attribute> {
+/// Parse an attribute of the graph `graph_id`.
+fn attribute<'a>(input: &mut &'a str, graph_id: usize, spans: &mut SpanRecorder) -> ModalResult<Attribute<'a>> {
+    ws0.parse_next(input)?;
+    let before = input.len();
     let (r#type, name, value) = delimited(
-        (multispace0, '(', multispace0),
+        ('(', ws0),
         (
-            terminated(property_type, multispace1),
-            terminated(parse_string, multispace1),
+            terminated(property_type, ws1),
+            terminated(parse_string, ws1),
             parse_string,
         ),
-        (multispace0, ')', multispace0),
+        (ws0, ')'),
     ).parse_next(input)?;
+    spans.attribute(graph_id, &name, before, input.len());
+    ws0.parse_next(input)?;
 
     Ok(Attribute{r#type, name, value})
 }
 
-fn attributes(input: &mut &str) -> ModalResult<Attributes> {
-    fn attributes_inner(input: &mut &str) -> ModalResult<Attributes> {
-        let graph_id: usize = delimited(multispace0, dec_uint, multispace1).parse_next(input)?;
-    
-        repeat(.., terminated(attribute, multispace0))
-            .map(Attributes)
-            .parse_next(input)    
+fn attributes_list<'a>(input: &mut &'a str, graph_id: usize, spans: &mut SpanRecorder) -> ModalResult<Attributes<'a>> {
+    repeat(.., terminated(|i: &mut &'a str| attribute(i, graph_id, spans), ws0))
+        .map(Attributes)
+        .parse_next(input)
+}
+
+/// Parse the attributes of a graph, with the identifier of the graph they belong to.
+fn attributes<'a>(input: &mut &'a str, spans: &mut SpanRecorder) -> ModalResult<(usize, Attributes<'a>)> {
+    fn attributes_inner<'a>(input: &mut &'a str, spans: &mut SpanRecorder) -> ModalResult<(usize, Attributes<'a>)> {
+        let graph_id: usize = delimited(ws0, dec_uint, ws1).parse_next(input)?;
+        let attributes = attributes_list(input, graph_id, spans)?;
+        Ok((graph_id, attributes))
+    }
+
+    parse_tag("graph_attributes", |i: &mut &'a str| attributes_inner(i, spans)).parse_next(input)
+}
+
+/// Parse the `displaying` block of the older versions, which holds the
+/// rendering parameters of the root graph as attributes.
+fn displaying<'a>(input: &mut &'a str, spans: &mut SpanRecorder) -> ModalResult<(usize, Attributes<'a>)> {
+    parse_tag("displaying", |i: &mut &'a str| attributes_list(i, 0, spans))
+        .map(|attributes| (0, attributes))
+        .parse_next(input)
+}
+
+/// Parse a block of attributes, in either of its forms.
+fn attributes_block<'a>(input: &mut &'a str, spans: &mut SpanRecorder) -> ModalResult<(usize, Attributes<'a>)> {
+    let checkpoint = spans.checkpoint();
+    if Cursor::new(input).peek_tag("displaying") {
+        displaying(input, spans)
+    } else {
+        attributes(input, spans)
+    }.inspect_err(|_| spans.rollback(checkpoint))
+}
+
+/// The tags of the top-level blocks, anything else is unknown.
+const KNOWN_TAGS: [&str; 11] = [
+    "date", "author", "comments", "nb_nodes", "nodes", "nb_edges", "edge",
+    "cluster", "property", "graph_attributes", "displaying"
+];
+
+/// The tags of the blocks the section `after` repeats, that the parser stops at when they are malformed.
+fn section_tags(after: Section) -> &'static [&'static str] {
+    match after {
+        Section::Clusters => &["cluster"],
+        Section::Properties => &["property"],
+        Section::Attributes => &["graph_attributes", "displaying"],
+        _ => &[]
     }
+}
+
+/// Read the unknown top-level blocks that follow the section `after`, if any, and keep them.
+///
+/// A block of the section itself is malformed, since the section stopped
+/// before it: it is kept too, and the result tells the section to go on after it.
+fn unknown_blocks<'a>(input: &mut &'a str, after: Section, unknown: &mut Vec<UnknownBlock<'a>>) -> ModalResult<bool> {
+    loop {
+        let mut lookahead = Cursor::new(input);
+        let tag = lookahead.eat(b'(').and_then(|()| {
+            lookahead.ws0();
+            lookahead.atom()
+        });
+        let malformed = tag.is_some_and(|tag| section_tags(after).contains(&tag));
+        if !malformed && tag.is_none_or(|tag| KNOWN_TAGS.contains(&tag)) {
+            return Ok(false);
+        }
 
-    parse_tag("graph_attributes", attributes_inner).parse_next(input)
+        let start = *input;
+        let expr = scan(input, scan_sexpr)?;
+        let source = &start[..start.len() - input.len()];
+        ws0.parse_next(input)?;
+        let kind = if malformed { "Malformed" } else { "Unknown" };
+        eprintln!("[WARNING] {kind} block ({}) kept as is", expr.tag().unwrap_or_default());
+        unknown.push(UnknownBlock::new(expr, source, after));
+        if malformed {
+            return Ok(true);
+        }
+    }
 }
 
-fn graph(input: &mut &str) -> ModalResult<Graph> {
+fn graph<'a>(input: &mut &'a str, options: &ParseOptions, spans: &mut SpanRecorder) -> ModalResult<Graph<'a>> {
 
-    fn inner_graph(input: &mut &str) -> ModalResult<Graph> {
-        let version = terminated(parse_string, multispace0).parse_next(input)?;
+    fn inner_graph<'a>(input: &mut &'a str, options: &ParseOptions, spans: &mut SpanRecorder) -> ModalResult<Graph<'a>> {
+        let mut unknown = Vec::new();
+        // the unknown blocks may be anywhere between the known ones
+        let mut skip_unknown = |input: &mut &'a str, after| if options.lenient {
+            unknown_blocks(input, after, &mut unknown)
+        } else {
+            Ok(false)
+        };
+
+        let version = terminated(
+            cut_err(parse_string.verify_map(|v| TlpVersion::from_str(&v).ok()))
+                .context(StrContext::Expected(StrContextValue::Description("a supported tlp version: 2.0, 2.1, 2.2 or 2.3"))),
+            ws0
+        ).parse_next(input)?;
+        skip_unknown(input, Section::Version)?;
         
         // TODO handle random ordering
-        let date = (opt(terminated(date, multispace0)).parse_next(input))?;
-        let comments = (opt(terminated(comments, multispace0)).parse_next(input))?;
-
-        let nodes = (terminated(nodes_amount_and_ids, multispace0).context(winnow::error::StrContext::Label("Nodes parsing")).parse_next(input))?;
-        let edges = (terminated(edges, multispace0).context(winnow::error::StrContext::Label("Edges parsing")).parse_next(input))?;
+        let date = (opt(terminated(date, ws0)).parse_next(input))?;
+        skip_unknown(input, Section::Date)?;
+        let author = (opt(terminated(author, ws0)).parse_next(input))?;
+        skip_unknown(input, Section::Author)?;
+        let comments = (opt(terminated(comments, ws0)).parse_next(input))?;
+        skip_unknown(input, Section::Comments)?;
+
+        let nodes = (terminated(|i: &mut &str| nodes_amount_and_ids(i, version), ws0).context(winnow::error::StrContext::Label("Nodes parsing")).parse_next(input))?;
+        skip_unknown(input, Section::Nodes)?;
+        let edges = (terminated(|i: &mut &str| edges(i, spans), ws0).context(winnow::error::StrContext::Label("Edges parsing")).parse_next(input))?;
+        skip_unknown(input, Section::Edges)?;
 
         // TODO check the edges are valid in comparison to nodes
 
         // TODO handle a different ordering
-        let clusters = opt(terminated(clusters, multispace0)).parse_next(input)?;
-        let properties = opt(terminated(properties, multispace0)).parse_next(input)?;
-        let attributes = opt(terminated(attributes, multispace0)).parse_next(input)?;
-        
+        // in lenient mode, a section goes on after its malformed blocks
+        let mut read_clusters = opt(terminated(|i: &mut &'a str| clusters(i, version, spans), ws0)).parse_next(input)?;
+        while skip_unknown(input, Section::Clusters)? {
+            if let Some(more) = opt(terminated(|i: &mut &'a str| clusters(i, version, spans), ws0)).parse_next(input)? {
+                read_clusters.get_or_insert_with(|| Clusters(Vec::new())).0.extend(more.0);
+            }
+        }
+        let mut read_properties = opt(terminated(|i: &mut &'a str| properties(i, nodes.len(), spans), ws0)).parse_next(input)?;
+        while skip_unknown(input, Section::Properties)? {
+            let more = terminated(|i: &mut &'a str| properties(i, nodes.len(), spans), ws0).parse_next(input)?;
+            read_properties.get_or_insert_with(|| Properties(Vec::new())).0.extend(more.0);
+        }
+        let mut blocks: Vec<_> = repeat(.., terminated(|i: &mut &'a str| attributes_block(i, spans), ws0)).parse_next(input)?;
+        while skip_unknown(input, Section::Attributes)? {
+            let more: Vec<_> = repeat(.., terminated(|i: &mut &'a str| attributes_block(i, spans), ws0)).parse_next(input)?;
+            blocks.extend(more);
+        }
+        let (mut clusters, properties) = (read_clusters, read_properties);
+
+        let mut attributes: Option<Attributes> = None;
+        for (graph_id, block) in blocks {
+            let target = if graph_id == 0 {
+                Some(&mut attributes)
+            } else {
+                clusters.as_mut()
+                    .and_then(|c| c.0.iter_mut().find_map(|c| c.find_mut(graph_id)))
+                    .map(|c| &mut c.attributes)
+            };
+            match target {
+                Some(Some(existing)) => existing.0.extend(block.0),
+                Some(target) => *target = Some(block),
+                None => eprintln!("[WARNING] Attributes of the unknown graph {graph_id} are ignored"),
+            }
+        }
+
         Ok(Graph{
             version,
             nodes,
@@ -599,25 +1066,35 @@ fn graph(input: &mut &str) -> ModalResult<Graph> {
             attributes,
             clusters,
 
-            author: None,
+            author,
             comments,
-            date
+            date,
+
+            unknown,
+            source_map: None
         })
     }
 
-    terminated(parse_tag("tlp", inner_graph), multispace0).parse_next(input)
+    // a byte order mark is left by some Windows editors
+    let _ = opt('\u{feff}').parse_next(input)?;
+    delimited(ws0, parse_tag("tlp", |i: &mut &'a str| inner_graph(i, options, spans)), ws0).parse_next(input)
 }
 
 #[cfg(test)]
+#[allow(unused_variables, suspicious_double_ref_op)]
+#[allow(clippy::into_iter_on_ref, clippy::needless_borrow, clippy::unnecessary_mut_passed)]
 mod test {
+    use std::borrow::Cow;
+
     use winnow::Parser;
 
-    use crate::{cluster, edge, edges_ids, graph, nodes_ids, parse_ids, parse_ids_bloc, parse_ids_list, parse_ids_range, parse_string, property, property_default, property_for_node, property_type, Edge, IdsBloc, IdsList, IdsRange, NodesIds};
+    use crate::span::SpanRecorder;
+    use crate::{cluster, edge, edges, edges_ids, graph, nodes_ids, parse_ids, parse_ids_bloc, parse_ids_list, parse_ids_range, parse_string, property, property_default, property_for_node, property_type, Coord, Edge, Graph, IdsRange, NodesIds, ParseOptions, PropertyType, TlpVersion, Value, WriteOptions};
 
     #[test]
     fn test_nodes_list() {
         let  mut repr = "(nodes 0 1 2 3 4 5 )";
-        let nodes: NodesIds = nodes_ids(&mut repr).unwrap();
+        let nodes: NodesIds = nodes_ids(&mut repr, TlpVersion::V2_3, 0).unwrap();
         assert_eq!(
             nodes.to_vec(),
             (0..=5).collect::<Vec<usize>>()
@@ -644,14 +1121,20 @@ mod test {
 
 ];
         for repr in reprs.iter() {
-            let prop = property(&mut repr.clone()).unwrap();
+            let prop = property(&mut repr.clone(), 0, &mut SpanRecorder::default()).unwrap();
         }
     }
 
     #[test]
     fn test_node_property() {
-        property_for_node(&mut r#"(node 0 "(11,-6,0)\")"# ).unwrap();
-            
+        let p = property_for_node(&mut r#"(node 0 "(11,-6,0)")"# ).unwrap();
+        assert_eq!(p.value, "(11,-6,0)");
+
+        // the backslash escapes the closing quote: the string never ends
+        assert!(property_for_node(&mut r#"(node 0 "(11,-6,0)\")"# ).is_err());
+
+        let p = property_for_node(&mut r#"(node 0 "say \"hi\" (\\o/)\nbye")"# ).unwrap();
+        assert_eq!(p.value, "say \"hi\" (\\o/)\nbye");
     }
 
     #[test]
@@ -668,10 +1151,80 @@ mod test {
 
 ];
         for repr in reprs.iter() {
-            let prop = property(&mut repr.clone()).unwrap();
+            let prop = property(&mut repr.clone(), 0, &mut SpanRecorder::default()).unwrap();
         }
     }
 
+    #[test]
+    fn test_property_with_edges() {
+        let mut repr = r#"(property  0 double "weight"
+(default "1" "0.5")
+(node 3 "2")
+(edge 0 "0.25")
+(node 1 "3")
+(edge 7 "8")
+)"#;
+        let prop = property(&mut repr, 0, &mut SpanRecorder::default()).unwrap();
+        assert_eq!(prop.nodes_property.iter().map(|p| p.id).collect::<Vec<_>>(), vec![3, 1]);
+        assert_eq!(prop.edges_property.iter().map(|p| (p.id, &*p.value)).collect::<Vec<_>>(), vec![(0, "0.25"), (7, "8")]);
+    }
+
+    #[test]
+    fn test_edges_stop_on_malformed_line() {
+        let mut repr = "(nb_edges 3)\n(edge 0 1 2)\n(edge 1 2 x)\n(edge 2 3 4)";
+        let parsed = edges(&mut repr, &mut SpanRecorder::default()).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert!(repr.starts_with("(edge 1 2 x)"));
+    }
+
+    #[test]
+    fn test_comments_anywhere() {
+        let repr = r#"; exported by hand
+(tlp "2.3" ;version
+(nb_nodes 3)
+;(nodes <node_id> <node_id> ...)
+(nodes 0..2)
+(nb_edges 2)
+(edge 0 0 1) ; first edge
+;(edge 1 1 1)
+(edge 1 1 2)
+; clusters
+(cluster 1 ; named later
+(nodes 0 ;1
+1)
+;(edges)
+(edges 0)
+)
+(property  0 string "viewLabel"
+(default "" "") ; no default
+;(node 0 "skipped")
+(node 1 "a;b")
+)
+; between properties
+(property  0 int "viewShape"
+(default "18" "0")
+(edge 1 "4") ;after an edge
+)
+(graph_attributes 0 ; attributes
+(string "name" "commented") ;
+)
+) ; the end"#;
+        let graph = Graph::parse_borrowed(repr).unwrap();
+        assert_eq!(graph.nodes().to_vec(), vec![0, 1, 2]);
+        assert_eq!(graph.edges.len(), 2);
+
+        let clusters = graph.clusters.as_ref().unwrap();
+        assert_eq!(clusters.0[0].nodes.to_vec(), vec![0, 1]);
+        assert_eq!(clusters.0[0].edges.to_vec(), vec![0]);
+
+        let properties = &graph.properties.as_ref().unwrap().0;
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties[0].nodes_property.len(), 1);
+        assert_eq!(properties[0].nodes_property[0].value, "a;b");
+        assert_eq!(properties[1].edges_property[0].value, "4");
+        assert_eq!(graph.attributes.as_ref().unwrap().0[0].value, "commented");
+    }
+
     #[test]
     fn test_types() {
         let mut reprs = [
@@ -701,7 +1254,7 @@ mod test {
     #[test]
     fn test_nodes_range() {
         let mut repr = "(nodes 0..5)";
-        let nodes = nodes_ids(&mut repr).unwrap();
+        let nodes = nodes_ids(&mut repr, TlpVersion::V2_3, 0).unwrap();
         assert_eq!(
             nodes.to_vec(),
             IdsRange(0..=5).to_vec()
@@ -709,10 +1262,22 @@ mod test {
     }
 
 
+    #[test]
+    fn test_nodes_range_up_to_usize_max() {
+        let mut repr = "(nodes 18446744073709551615..18446744073709551615)";
+        assert_eq!(nodes_ids(&mut repr, TlpVersion::V2_3, 0).unwrap().to_vec(), vec![usize::MAX]);
+
+        // a usize cannot count all of them
+        let mut repr = "(nodes 0..18446744073709551615)";
+        assert!(nodes_ids(&mut repr, TlpVersion::V2_3, 0).is_err());
+        let mut repr = "(nodes 0..9223372036854775807 9223372036854775808..18446744073709551615)";
+        assert!(nodes_ids(&mut repr, TlpVersion::V2_3, 0).is_err());
+    }
+
     #[test]
     fn test_edges_range() {
         let mut repr = "(edges 0..70708)";
-        let edges = edges_ids(&mut repr).unwrap();
+        let edges = edges_ids(&mut repr, TlpVersion::V2_3).unwrap();
         assert_eq!(
             edges.to_vec(),
             IdsRange(0..=70708).to_vec()
@@ -721,6 +1286,9 @@ mod test {
 
     #[test]
     fn test_ids() {
+        let mut parse_ids = |i: &mut &str| parse_ids(i, TlpVersion::V2_3, 0);
+        let mut parse_ids_list = |i: &mut &str| parse_ids_list(i, 0);
+        let mut parse_ids_bloc = |i: &mut &str| parse_ids_bloc(i, 0);
         parse_ids_list.parse(&mut "37830 37829").unwrap();
         parse_ids_range.parse(&mut "37830..37829").unwrap();
         parse_ids_bloc.parse(&mut "37830 37829").unwrap();
@@ -733,13 +1301,13 @@ mod test {
     #[test]
     fn test_nodes_list2() {
         let mut repr = "37830 37829 37828 37827 37826 37825 37824 37823 37822 37821 37820 37819 37818 37817 37816 37815 37814 37813 37812 37811 37810 37809 37808 37807 37806 37805 37804 37803 37802 37801 37800 37799 37798 37797 37796 37795 37794 37793 37792 37791 37790 37789 37788 37787 37786 37785 37784 37783 37782 37781 37780 37779 37778 37777 37776 37775 37774 37773 37772 37771 37770 37769 37768 37767 37766 37765 37764 37763 37762 37761 37760 37759 37758 37757 37756 37755 37754 37753 37752 37751 37750 37749 37748 37747 37746 37745 37744 37743 37742 37741 37740 37739 37738 37737 37736 37735 37734 37733 37732 37731 37730 37729 37728 37727 37726 37725 37724 37723 37722 37721 37720 37719 37718 37717 37716 37715 37714 37713 37712 37711 37710 37709 37708 37707 37706 37705 37704 37703 37702 37701 37700 37699 37698 37697 37696 37695 37694 37693 37692 37691 37690 37689 37688 37687 37686 37685 37684 37683 37682 37681 37680 37679 37678 37677 37676 37675 37674 37673 37672 37671 37670 37669 37668 37667 37666 37665 37664 37663 37662 37661 37660 37659 37658 37657 37656 37655 37654 37653 37652 37651 37650 37649 37648 37647 37646 37645 37644 37643 37642 37641 37640 37639 37638 37637 37636 37635 37634 37633 37632 37631 37630 37629 37628 37627 37626 37625 37624 37623 37622 37621 37620 37619 37618 37617 37616 37615 37614 37613 37612 37611 37610 37609 37608 37607 37606 37605 37604 37603 37602 37601 37600 37599 37598 37597 37596 37595 37594 37593 37592 37591 37590 37589 37588 37587 37586 37585 37584 37583 37582 37581 37580 37579 37578 37577 37576 37575 37574 37573 37572 37571 37570 37569 37568 37567 37566 37565 37564 37563 37562 37561 37560 37559 37558 37557 37556 37555 37554 37553 37552 37551 37550 37549 37548 37547 37546 37545 37544 37543 37542 37541 37540 37539 37538 37537 37536 37535 37534 37533 37532 37531 37530 37529 37528 37527 37526 37525 37524 37523 37522 37521 37520 37519 37518 37517 37516 37515 37514 37513 37512 37511 37510 37509 37508 37507 37506 37505 37504 37503 37502 37501 37500 37499 37498 37497 37496 37495 37494 37493 37492 37491 37490 37489 37488 37487 37486 37485 37484 37483 37482 37481 37480 37479 37478 37477 37476 37475 37474 37473 37472 37471 37470 37469 37468 37467 37466 37465 37464 37463 37462 37461 37460 37459 37458 37457 37456 37455 37454 37453 37452 37451 37450 37449 37448 37447 37446 37445 37444 37443 37442 37441 37440 37439 37438 37437 37436 37435 37434 37433 37432 37431 2002..2820 37314 2821 37315 2822 37316 2823 37317 2824 37318 2825 37319 2826 37320 2827 37321 2828 37322 2829 37323 2830 37324 2831 37325 2832 37326 2833 37327 2834 37328 2835 37329 2836 37330 2837 37331 2838 37332 2839 37333 2840 37334 2841 37335 2842 37336 2843 37337 2844 37338 2845 37339 2846 37340 2847 37341 2848 37342 2849 37343 2850 37344 2851 37345 2852 37346 2853 37347 2854 37348 2855 37349 2856 37350 2857 37351 2858 37352 2859 37353 2860 37354 2861 37355 2862 37356 2863 37357 2864 37358 2865 37359 2866 37360 2867 37361 2868 37362 2869 37363 2870 37364 2871 37365 2872 37366 2873 37367 2874 37368 2875 37369 2876 37370 2877 37371 2878 37372 2879 37373 2880 37374 2881 37375 2882 37376 2883 37377 2884 37378 2885 37379 2886 37380 2887 37381 2888 37382 2889 37383 2890 37384 2891 37385 2892 37386 2893 37387 2894 37388 2895 37389 2896 37390 2897 37391 2898 37392 2899 37393 2900 37394 2901 37395 2902 37396 2903 37397 2904 37398 2905 37399 2906 37400 2907 37401 2908 37402 2909 37403 2910 37404 2911 37405 2912 37406 2913 37407 2914 37408 2915 37409 2916 37410 2917 37411 2918 37412 2919 37413 2920 37414 2921 37415 2922 37416 2923 37417 2924 37418 2925 37419 2926 37420 2927 37421 2928 37422 2929 37423 2930 37424 2931 37425 2932 37426 2933 37427 2934 37428 2935 37429 2936 37430 2937";
-        let list = parse_ids(&mut repr).unwrap();
+        let list = parse_ids(&mut repr, TlpVersion::V2_3, 0).unwrap();
         
         dbg!(&repr);
         assert_eq!(repr.len(), 0);
 
         let mut repr = "(nodes 37830 37829 37828 37827 37826 37825 37824 37823 37822 37821 37820 37819 37818 37817 37816 37815 37814 37813 37812 37811 37810 37809 37808 37807 37806 37805 37804 37803 37802 37801 37800 37799 37798 37797 37796 37795 37794 37793 37792 37791 37790 37789 37788 37787 37786 37785 37784 37783 37782 37781 37780 37779 37778 37777 37776 37775 37774 37773 37772 37771 37770 37769 37768 37767 37766 37765 37764 37763 37762 37761 37760 37759 37758 37757 37756 37755 37754 37753 37752 37751 37750 37749 37748 37747 37746 37745 37744 37743 37742 37741 37740 37739 37738 37737 37736 37735 37734 37733 37732 37731 37730 37729 37728 37727 37726 37725 37724 37723 37722 37721 37720 37719 37718 37717 37716 37715 37714 37713 37712 37711 37710 37709 37708 37707 37706 37705 37704 37703 37702 37701 37700 37699 37698 37697 37696 37695 37694 37693 37692 37691 37690 37689 37688 37687 37686 37685 37684 37683 37682 37681 37680 37679 37678 37677 37676 37675 37674 37673 37672 37671 37670 37669 37668 37667 37666 37665 37664 37663 37662 37661 37660 37659 37658 37657 37656 37655 37654 37653 37652 37651 37650 37649 37648 37647 37646 37645 37644 37643 37642 37641 37640 37639 37638 37637 37636 37635 37634 37633 37632 37631 37630 37629 37628 37627 37626 37625 37624 37623 37622 37621 37620 37619 37618 37617 37616 37615 37614 37613 37612 37611 37610 37609 37608 37607 37606 37605 37604 37603 37602 37601 37600 37599 37598 37597 37596 37595 37594 37593 37592 37591 37590 37589 37588 37587 37586 37585 37584 37583 37582 37581 37580 37579 37578 37577 37576 37575 37574 37573 37572 37571 37570 37569 37568 37567 37566 37565 37564 37563 37562 37561 37560 37559 37558 37557 37556 37555 37554 37553 37552 37551 37550 37549 37548 37547 37546 37545 37544 37543 37542 37541 37540 37539 37538 37537 37536 37535 37534 37533 37532 37531 37530 37529 37528 37527 37526 37525 37524 37523 37522 37521 37520 37519 37518 37517 37516 37515 37514 37513 37512 37511 37510 37509 37508 37507 37506 37505 37504 37503 37502 37501 37500 37499 37498 37497 37496 37495 37494 37493 37492 37491 37490 37489 37488 37487 37486 37485 37484 37483 37482 37481 37480 37479 37478 37477 37476 37475 37474 37473 37472 37471 37470 37469 37468 37467 37466 37465 37464 37463 37462 37461 37460 37459 37458 37457 37456 37455 37454 37453 37452 37451 37450 37449 37448 37447 37446 37445 37444 37443 37442 37441 37440 37439 37438 37437 37436 37435 37434 37433 37432 37431 2002..2820 37314 2821 37315 2822 37316 2823 37317 2824 37318 2825 37319 2826 37320 2827 37321 2828 37322 2829 37323 2830 37324 2831 37325 2832 37326 2833 37327 2834 37328 2835 37329 2836 37330 2837 37331 2838 37332 2839 37333 2840 37334 2841 37335 2842 37336 2843 37337 2844 37338 2845 37339 2846 37340 2847 37341 2848 37342 2849 37343 2850 37344 2851 37345 2852 37346 2853 37347 2854 37348 2855 37349 2856 37350 2857 37351 2858 37352 2859 37353 2860 37354 2861 37355 2862 37356 2863 37357 2864 37358 2865 37359 2866 37360 2867 37361 2868 37362 2869 37363 2870 37364 2871 37365 2872 37366 2873 37367 2874 37368 2875 37369 2876 37370 2877 37371 2878 37372 2879 37373 2880 37374 2881 37375 2882 37376 2883 37377 2884 37378 2885 37379 2886 37380 2887 37381 2888 37382 2889 37383 2890 37384 2891 37385 2892 37386 2893 37387 2894 37388 2895 37389 2896 37390 2897 37391 2898 37392 2899 37393 2900 37394 2901 37395 2902 37396 2903 37397 2904 37398 2905 37399 2906 37400 2907 37401 2908 37402 2909 37403 2910 37404 2911 37405 2912 37406 2913 37407 2914 37408 2915 37409 2916 37410 2917 37411 2918 37412 2919 37413 2920 37414 2921 37415 2922 37416 2923 37417 2924 37418 2925 37419 2926 37420 2927 37421 2928 37422 2929 37423 2930 37424 2931 37425 2932 37426 2933 37427 2934 37428 2935 37429 2936 37430 2937) ";
-        let nodes = nodes_ids(&mut repr).unwrap();
+        let nodes = nodes_ids(&mut repr, TlpVersion::V2_3, 0).unwrap();
     }
 
 
@@ -813,10 +1381,333 @@ mod test {
 
         for repr in reprs.into_iter() {
             dbg!(repr);
-            cluster(&mut repr.clone()).unwrap();
+            cluster(&mut repr.clone(), TlpVersion::V2_3, &mut SpanRecorder::default()).unwrap();
+        }
+    }
+
+
+    #[test]
+    fn test_borrowed_graph() {
+        let repr = r#"(tlp "2.3"
+(nodes 0 1)
+(edge 0 0 1)
+(property  0 string "viewLabel"
+(default "" "")
+(node 1 "second")
+)
+)"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        let properties = g.properties.as_ref().unwrap();
+        let label = &properties.0[0].nodes_property[0].value;
+        assert!(matches!(label, Cow::Borrowed(_)));
+        assert!(repr.as_bytes().as_ptr_range().contains(&label.as_ptr()));
+
+        let owned = Graph::parse_borrowed(repr).unwrap().into_owned();
+        assert_eq!(owned, g);
+        assert_eq!(owned, repr.parse::<Graph>().unwrap());
+    }
+
+    #[test]
+    fn test_labels_round_trip() {
+        let labels = [
+            r#"say "hi""#,
+            r"C:\Users\",
+            "(nested (parentheses))",
+            "first line\nsecond line\r\n",
+            "tab\tand \\n literally",
+            "ünïcödé ✓ 漢字",
+            "",
+        ];
+
+        let mut repr = String::from("(tlp \"2.3\"\n(nodes 0..6)\n(property  0 string \"viewLabel\"\n(default \"\" \"\")\n");
+        for (id, label) in labels.iter().enumerate() {
+            repr.push_str(&format!("(node {id} "));
+            crate::writer::write_string(&mut repr, label).unwrap();
+            repr.push_str(")\n");
         }
+        repr.push_str(")\n)\n");
+
+        let g = Graph::parse_borrowed(&repr).unwrap();
+        let values: Vec<&str> = g.properties.as_ref().unwrap().0[0].nodes_property.iter()
+            .map(|p| &*p.value)
+            .collect();
+        assert_eq!(values, labels);
+
+        let written = g.to_string();
+        assert_eq!(Graph::parse_borrowed(&written).unwrap(), g);
     }
 
+    #[test]
+    fn test_header_round_trip() {
+        let repr = r#"(tlp "2.3"
+(date "18-10-2026")
+(author "Jane \"JD\" Doe")
+(comments "first line\nsecond line")
+(nb_nodes 1)
+;(nodes <node_id> <node_id> ...)
+(nodes 0)
+(nb_edges 0)
+;(edge <edge_id> <source_id> <target_id>)
+)
+"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        assert_eq!(g.author.as_ref().unwrap().0, r#"Jane "JD" Doe"#);
+        assert_eq!(g.comments.as_ref().unwrap().0, "first line\nsecond line");
+        assert_eq!(g.to_string(), repr);
+    }
+
+    #[test]
+    fn test_vector_properties() {
+        let repr = r#"(tlp "2.3"
+(nb_nodes 2)
+;(nodes <node_id> <node_id> ...)
+(nodes 0..1)
+(nb_edges 1)
+;(edge <edge_id> <source_id> <target_id>)
+(edge 0 0 1)
+(property  0 vector<double> "embedding"
+(default "()" "()")
+(node 0 "(1.5, 2, 3)")
+)
+(property  0 vector<string> "tags"
+(default "()" "(\"edge\")")
+(node 1 "(\"a\", \"b \\\"c\\\"\")")
+)
+)
+"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+
+        let embedding = g.property("embedding").unwrap();
+        assert_eq!(*embedding.property_type(), PropertyType::DoubleVector);
+        assert_eq!(embedding.node_value(0).unwrap(), Value::DoubleVector(vec![1.5, 2., 3.]));
+        assert_eq!(embedding.node_value(1).unwrap(), Value::DoubleVector(vec![]));
+
+        let tags = g.property("tags").unwrap();
+        assert_eq!(tags.node_value(1).unwrap().to_string(), r#"("a", "b \"c\"")"#);
+        assert_eq!(tags.edge_value(0).unwrap(), Value::StringVector(vec![Cow::Borrowed("edge")]));
+
+        assert_eq!(g.to_string(), repr);
+    }
+
+    #[test]
+    fn test_edge_polyline() {
+        let repr = r#"(tlp "2.3"
+(nodes 0..2)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 3 2 0)
+(property  0 layout "viewLayout"
+(default "(0,0,0)" "()")
+(node 1 "(10,0,0)")
+(node 2 "(10,10,0)")
+(edge 1 "((12,2,0),(12,8,0))")
+)
+)
+"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        let coord = |x, y| Coord { x, y, z: 0. };
+
+        assert_eq!(g.edge_polyline(0).unwrap(), vec![coord(0., 0.), coord(10., 0.)]);
+        assert_eq!(g.edge_polyline(1).unwrap(), vec![coord(10., 0.), coord(12., 2.), coord(12., 8.), coord(10., 10.)]);
+        assert_eq!(g.edge_polyline(3).unwrap(), vec![coord(10., 10.), coord(0., 0.)]);
+        assert!(g.edge_polyline(2).is_err());
+
+        let layout = g.property("viewLayout").unwrap();
+        assert_eq!(layout.edge_value(1).unwrap().to_string(), "((12,2,0),(12,8,0))");
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let error = Graph::parse_borrowed("(tlp \"3.0\"\n(nodes 0)\n)").unwrap_err();
+        assert!(error.contains("a supported tlp version"), "{error}");
+    }
+
+    #[test]
+    fn test_legacy_version() {
+        let repr = r#"(tlp "2.0"
+(nb_nodes 3)
+;(nodes <node_id> <node_id> ...)
+(nodes 0 1 2)
+(nb_edges 1)
+;(edge <edge_id> <source_id> <target_id>)
+(edge 0 0 1)
+(cluster 1 "first"
+(nodes 0 1 2)
+(edges 0)
+(cluster 2 "second"
+(nodes 0)
+(edges )
+)
+)
+(displaying
+(color "backgroundColor" "(255,255,255,255)")
+)
+)
+"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        assert_eq!(g.version(), TlpVersion::V2_0);
+
+        let clusters = &g.clusters.as_ref().unwrap().0;
+        assert_eq!(clusters[0].name(), Some("first"));
+        assert_eq!(clusters[0].clusters[0].name(), Some("second"));
+        assert_eq!(g.attributes.as_ref().unwrap().0[0].name, "backgroundColor");
+
+        // the displaying block is written back as it is in 2.0
+        let written = g.to_string();
+        assert!(written.contains("(cluster 1 \"first\"\n(nodes 0 1 2)\n"), "{written}");
+        assert!(written.contains("(displaying\n(color \"backgroundColor\" \"(255,255,255,255)\")\n)\n"), "{written}");
+        assert!(!written.contains("graph_attributes"), "{written}");
+        assert!(!written.contains("\"name\""), "{written}");
+        assert_eq!(Graph::parse_borrowed(&written).unwrap(), g);
+
+        let mut written = String::new();
+        g.write_with(&mut written, &WriteOptions { version: Some(TlpVersion::V2_3) }).unwrap();
+        assert!(written.starts_with("(tlp \"2.3\"\n"), "{written}");
+        assert!(written.contains("(cluster 1\n(nodes 0 1 2)\n"), "{written}");
+        assert!(written.contains("(graph_attributes 2 \n(string \"name\" \"second\")\n)\n"), "{written}");
+        assert!(written.contains("(graph_attributes 0 \n(color \"backgroundColor\" \"(255,255,255,255)\")\n)\n"), "{written}");
+        assert!(!written.contains("displaying"), "{written}");
+
+        let upgraded = Graph::parse_borrowed(&written).unwrap();
+        assert_eq!(upgraded.clusters, g.clusters);
+    }
+
+    #[test]
+    fn test_legacy_syntax_in_its_versions_only() {
+        let named = r#"(tlp "2.3"
+(nodes 0 1)
+(cluster 1 "first"
+(nodes 0)
+(edges )
+)
+)"#;
+        let error = Graph::parse_borrowed(named).unwrap_err();
+        assert!(error.contains("a cluster named in its graph_attributes after tlp 2.0"), "{error}");
+        assert!(Graph::parse_borrowed(&named.replace("2.3", "2.0")).is_ok());
+
+        let ranges = r#"(tlp "2.1"
+(nodes 0..3)
+(edge 0 0 1)
+)"#;
+        let error = Graph::parse_borrowed(ranges).unwrap_err();
+        assert!(error.contains("identifiers listed one by one before tlp 2.2"), "{error}");
+        assert!(Graph::parse_borrowed(&ranges.replace("2.1", "2.2")).is_ok());
+
+        let cluster_ranges = "(tlp \"2.0\"\n(nodes 0 1 2)\n(cluster 1 \"first\"\n(nodes 0..2)\n(edges )\n)\n)";
+        assert!(Graph::parse_borrowed(cluster_ranges).is_err());
+    }
+
+    #[test]
+    fn test_clusters_attributes() {
+        let repr = r#"(tlp "2.3"
+(nb_nodes 2)
+;(nodes <node_id> <node_id> ...)
+(nodes 0..1)
+(nb_edges 0)
+;(edge <edge_id> <source_id> <target_id>)
+(cluster 1
+(nodes 0)
+(edges )
+)
+(graph_attributes 0 
+(string "name" "root")
+)
+(graph_attributes 1 
+(string "name" "cluster")
+(int "size" "1")
+)
+)
+"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        let cluster = &g.clusters.as_ref().unwrap().0[0];
+        assert_eq!(cluster.name(), Some("cluster"));
+        assert_eq!(cluster.attributes.as_ref().unwrap().0.len(), 2);
+        assert_eq!(g.to_string(), repr);
+
+        let mut written = String::new();
+        g.write_with(&mut written, &WriteOptions { version: Some(TlpVersion::V2_1) }).unwrap();
+        assert!(written.contains("\n(nodes 0 1)\n"), "{written}");
+    }
+
+    #[test]
+    fn test_lenient_unknown_blocks() {
+        let repr = r#"(tlp "2.3"
+(nb_nodes 1)
+;(nodes <node_id> <node_id> ...)
+(nodes 0)
+(nb_edges 0)
+;(edge <edge_id> <source_id> <target_id>)
+(plugin "custom"   1)
+(property  0 string "viewLabel"
+(default "" "")
+)
+(controller
+  ; the layout and the comments are kept
+  (view "Node Link Diagram view" 0
+    (data (string "scene" "a \"quoted\" scene")))
+)
+)
+"#;
+        assert!(Graph::parse_borrowed(repr).is_err());
+
+        let lenient = ParseOptions { lenient: true, ..Default::default() };
+        let g = Graph::parse_with(repr, &lenient).unwrap();
+        let tags: Vec<_> = g.unknown_blocks().iter().map(|b| b.expr().tag().unwrap()).collect();
+        assert_eq!(tags, ["plugin", "controller"]);
+        assert_eq!(g.unknown_blocks()[0].source(), r#"(plugin "custom"   1)"#);
+        assert!(g.property("viewLabel").is_some());
+
+        // the blocks are written back untouched, after the same sections
+        let written = g.to_string();
+        assert!(written.ends_with(r#"(edge <edge_id> <source_id> <target_id>)
+(plugin "custom"   1)
+(property  0 string "viewLabel"
+(default "" "")
+)
+(controller
+  ; the layout and the comments are kept
+  (view "Node Link Diagram view" 0
+    (data (string "scene" "a \"quoted\" scene")))
+)
+)
+"#), "{written}");
+        assert_eq!(Graph::parse_with(&written, &lenient).unwrap(), g);
+    }
+
+    #[test]
+    fn test_lenient_malformed_blocks() {
+        let repr = r#"(tlp "2.0"
+(nodes 0 1)
+(cluster 1 "broken"
+(nodes x)
+(edges )
+)
+(cluster 2 "kept"
+(nodes 1)
+(edges )
+)
+(property  0 complex "broken"
+(default "" "")
+)
+(property  0 string "viewLabel"
+(default "" "")
+)
+)
+"#;
+        assert!(Graph::parse_borrowed(repr).is_err());
+
+        // the section goes on after the block it cannot read
+        let lenient = ParseOptions { lenient: true, ..Default::default() };
+        let g = Graph::parse_with(repr, &lenient).unwrap();
+        let tags: Vec<_> = g.unknown_blocks().iter().map(|b| b.expr().tag().unwrap()).collect();
+        assert_eq!(tags, ["cluster", "property"]);
+        assert!(g.find_cluster(1).is_none());
+        assert_eq!(g.find_cluster(2).unwrap().name(), Some("kept"));
+        assert!(g.property("viewLabel").is_some());
+        assert!(g.property("broken").is_none());
+
+        assert_eq!(Graph::parse_with(&g.to_string(), &lenient).unwrap(), g);
+    }
 
     #[test]
     fn test_graph() {
@@ -825,6 +1716,6 @@ mod test {
 (edge 0 1 0)
 (edge 1 0 2)
 )"#;
-        let g  = graph(&mut repr).unwrap();
+        let g  = graph(&mut repr, &ParseOptions::default(), &mut SpanRecorder::default()).unwrap();
     }
-}
\ No newline at end of file
+}
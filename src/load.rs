@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use crate::Graph;
+use crate::ParseOptions;
+
+impl Graph<'static> {
+    /// Read and parse a tlp file, see [Graph::load_with].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::load_with(path, &ParseOptions::default())
+    }
+
+    /// Read and parse a tlp file, whatever its origin: UTF-8 with or without
+    /// a byte order mark, or UTF-16 with one. In UTF-8 files, the bytes that
+    /// are not valid UTF-8 are read as Windows-1252, the Latin-1 of Windows
+    /// files (unless [ParseOptions::strict_encoding] is set).
+    pub fn load_with(path: impl AsRef<Path>, options: &ParseOptions) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        let text = decode(bytes, options.strict_encoding)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+
+        Graph::parse_with(&text, options)
+            .map(Graph::into_owned)
+    }
+}
+
+/// Decode the content of a file as text.
+pub(crate) fn decode(bytes: Vec<u8>, strict: bool) -> Result<String, String> {
+    let utf16 = match bytes.get(..2) {
+        Some([0xff, 0xfe]) => Some(u16::from_le_bytes as fn([u8; 2]) -> u16),
+        Some([0xfe, 0xff]) => Some(u16::from_be_bytes as fn([u8; 2]) -> u16),
+        _ => None
+    };
+    if let Some(from_bytes) = utf16 {
+        let (units, []) = bytes[2..].as_chunks::<2>() else {
+            return Err("Truncated UTF-16 content".to_string());
+        };
+        return String::from_utf16(&units.iter().map(|&unit| from_bytes(unit)).collect::<Vec<_>>())
+            .map_err(|e| format!("Invalid UTF-16 content: {e}"));
+    }
+
+    let e = match String::from_utf8(bytes) {
+        Ok(text) => return Ok(text),
+        Err(e) => e
+    };
+    let valid = e.utf8_error().valid_up_to();
+    let bytes = e.into_bytes();
+    let line = bytes[..valid].iter().filter(|&&b| b == b'\n').count() + 1;
+    if strict {
+        return Err(format!("Invalid UTF-8 at line {line} (byte {valid})"));
+    }
+
+    // a file mostly in UTF-8 keeps its valid characters, only the invalid bytes are decoded one by one
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = 0;
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(chunk.invalid().iter().map(|&b| windows_1252(b)));
+        invalid += chunk.invalid().len();
+    }
+    eprintln!("[WARNING] {invalid} bytes of invalid UTF-8 from line {line} (byte {valid}) are read as Windows-1252");
+    Ok(text)
+}
+
+/// The character of `byte` in Windows-1252: Latin-1 but for 0x80 to 0x9F,
+/// where the unassigned bytes are kept as the control characters of Latin-1.
+fn windows_1252(byte: u8) -> char {
+    const HIGH_CONTROLS: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+        '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH_CONTROLS[(byte - 0x80) as usize],
+        _ => byte as char
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("\u{feff}(tlp \"é\")".into(), true).unwrap(), "\u{feff}(tlp \"é\")");
+        assert_eq!(decode(b"(label \"caf\xe9\")".to_vec(), false).unwrap(), "(label \"café\")");
+
+        let error = decode(b"(tlp\n\"caf\xe9\")".to_vec(), true).unwrap_err();
+        assert_eq!(error, "Invalid UTF-8 at line 2 (byte 9)");
+
+        let utf16_le: Vec<u8> = [0xff, 0xfe].into_iter()
+            .chain("(tlp \"é\")".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(decode(utf16_le, true).unwrap(), "(tlp \"é\")");
+
+        let utf16_be: Vec<u8> = [0xfe, 0xff].into_iter()
+            .chain("(tlp)".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(decode(utf16_be, true).unwrap(), "(tlp)");
+        assert!(decode(vec![0xff, 0xfe, 0x28], true).is_err());
+    }
+
+    #[test]
+    fn test_decode_mixed_encodings() {
+        // UTF-8 labels, then a Latin-1 one and Windows quotes
+        let mut bytes = "(node 0 \"é ✓\")\n".as_bytes().to_vec();
+        bytes.extend(b"(node 1 \"caf\xe9 \x93quoted\x94\")\n");
+        bytes.extend("(node 2 \"naïve\")".as_bytes());
+        assert_eq!(decode(bytes, false).unwrap(), "(node 0 \"é ✓\")\n(node 1 \"café “quoted”\")\n(node 2 \"naïve\")");
+
+        // a truncated UTF-8 sequence is decoded byte by byte
+        assert_eq!(decode(b"\xc3(\xc3\xa9".to_vec(), false).unwrap(), "Ã(é");
+    }
+}
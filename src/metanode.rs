@@ -0,0 +1,346 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::iter::successors;
+
+use crate::Attribute;
+use crate::Attributes;
+use crate::Edge;
+use crate::EdgeProperty;
+use crate::Graph;
+use crate::Ids;
+use crate::NodeProperty;
+use crate::Property;
+use crate::PropertyType;
+use crate::SubgraphRef;
+use crate::Value;
+use crate::META_INNER_EDGES;
+use crate::VIEW_META_GRAPH;
+
+impl<'a> Graph<'a> {
+    /// The [VIEW_META_GRAPH] property of the root graph.
+    fn meta_graph_property(&self) -> Option<&Property<'a>> {
+        self.properties.as_ref()?
+            .0.iter()
+            .find(|p| p.graph_id == 0 && p.name == VIEW_META_GRAPH && p.r#type == PropertyType::Graph)
+    }
+
+    /// The cluster the metanode `node` stands for, none if it is not a metanode.
+    pub fn meta_graph(&self, node: usize) -> Option<SubgraphRef<'_, 'a>> {
+        match self.meta_graph_property()?.node_value(node) {
+            Ok(Value::Graph(id)) if id != 0 => self.find_cluster(id),
+            _ => None
+        }
+    }
+
+    /// The edges the meta-edge `edge` stands for, none if it is not a meta-edge.
+    pub fn meta_edges(&self, edge: usize) -> Option<Vec<usize>> {
+        match self.meta_graph_property()?.edge_value(edge) {
+            Ok(Value::EdgeSet(edges)) if !edges.is_empty() => Some(edges),
+            _ => None
+        }
+    }
+
+    /// The metanodes, with the clusters they stand for.
+    pub fn metanodes(&self) -> impl Iterator<Item=(usize, SubgraphRef<'_, 'a>)> {
+        self.meta_graph_property()
+            .into_iter()
+            .flat_map(|p| &p.nodes_property)
+            .filter_map(|p| Some((p.id, self.meta_graph(p.id)?)))
+    }
+
+    /// Collapse the cluster `cluster_id` into a metanode, as Tulip groups nodes,
+    /// and return the metanode.
+    ///
+    /// The metanode replaces the nodes of the cluster in the graph the cluster
+    /// is nested in, which Tulip does not allow to be the root graph. The edges
+    /// of that graph between the cluster and the other nodes are replaced by
+    /// meta-edges, one per other node and direction. The metanode and the
+    /// meta-edges are added to the graphs above too, which keep the nodes and
+    /// the edges of the cluster. The cluster itself moves up to become a
+    /// sibling of the graph of its metanode, and [VIEW_META_GRAPH] maps the
+    /// metanode to it and the meta-edges to the edges they stand for. The edges
+    /// of the graph between nodes of the cluster that are not edges of the
+    /// cluster are kept in its [META_INNER_EDGES] attribute, for [Graph::expand].
+    pub fn collapse(&mut self, cluster_id: usize) -> Result<usize, String> {
+        let subgraph = self.find_cluster(cluster_id)
+            .ok_or_else(|| format!("Unknown cluster {cluster_id}"))?;
+        let parent = subgraph.parent()
+            .ok_or_else(|| format!("The cluster {cluster_id} is in the root graph, where Tulip does not allow metanodes"))?;
+        let parent_id = parent.id();
+        let grandparent_id = parent.parent().map_or(0, |g| g.id());
+        let ancestors: Vec<usize> = successors(parent.parent(), SubgraphRef::parent)
+            .map(|g| g.id())
+            .collect();
+        let nodes: Ids = (**subgraph.nodes()).clone();
+        let cluster_edges: Ids = (**subgraph.edges()).clone();
+
+        // the nodes leave the parent, so they must leave the other clusters nested in it
+        let others = parent.children()
+            .filter(|c| c.id() != cluster_id)
+            .flat_map(|c| std::iter::once(c.clone()).chain(c.descendants()));
+        for other in others {
+            if let Some(node) = other.nodes().iter().find(|&node| nodes.contains(node)) {
+                return Err(format!("The node {node} of the cluster {cluster_id} is also in the cluster {}", other.id()));
+            }
+        }
+        if self.properties.iter().flat_map(|p| &p.0)
+            .any(|p| p.graph_id == 0 && p.name == VIEW_META_GRAPH && p.r#type != PropertyType::Graph) {
+            return Err(format!("The property {VIEW_META_GRAPH:?} is not a graph property"));
+        }
+
+        // the edges of the parent touching the cluster, and the meta-edges by other end and direction
+        let mut removed = Vec::new();
+        let mut inner = Vec::new();
+        let mut meta: BTreeMap<(usize, bool), Vec<usize>> = BTreeMap::new();
+        for id in parent.edges().iter() {
+            let Some(edge) = self.edge(id) else { continue };
+            match (nodes.contains(edge.src), nodes.contains(edge.tgt)) {
+                (true, true) => {
+                    removed.push(id);
+                    if !cluster_edges.contains(id) {
+                        inner.push(id as i64);
+                    }
+                }
+                (true, false) => {
+                    removed.push(id);
+                    meta.entry((edge.tgt, true)).or_default().push(id);
+                }
+                (false, true) => {
+                    removed.push(id);
+                    meta.entry((edge.src, false)).or_default().push(id);
+                }
+                (false, false) => {}
+            }
+        }
+
+        let metanode = self.nodes.last().map_or(0, |n| n + 1);
+        let first_edge = self.edges.iter().map(|e| e.id + 1).max().unwrap_or(0);
+        let meta_edges: Vec<(Edge, Vec<usize>)> = meta.into_iter()
+            .enumerate()
+            .map(|(i, ((other, outgoing), edges))| {
+                let (src, tgt) = if outgoing { (metanode, other) } else { (other, metanode) };
+                (Edge { id: first_edge + i, src, tgt }, edges)
+            })
+            .collect();
+        let meta_ids: Ids = meta_edges.iter().map(|(e, _)| e.id).collect();
+        let removed: Ids = removed.into_iter().collect();
+        let added = Ids::from(metanode..=metanode);
+
+        self.nodes.0 = self.nodes.union(&added);
+        self.edges.0.extend(meta_edges.iter().map(|(e, _)| e.clone()));
+        for id in ancestors {
+            let ancestor = self.cluster_mut(id).expect("an ancestor of the cluster");
+            ancestor.nodes.0 = ancestor.nodes.union(&added);
+            ancestor.edges.0 = ancestor.edges.union(&meta_ids);
+        }
+        let parent = self.cluster_mut(parent_id).expect("the parent of the cluster");
+        parent.nodes.0 = parent.nodes.difference(&nodes).union(&added);
+        parent.edges.0 = parent.edges.difference(&removed).union(&meta_ids);
+        let index = parent.clusters.iter().position(|c| c.id == cluster_id).expect("a child of the parent");
+        let mut cluster = parent.clusters.remove(index);
+        if !inner.is_empty() {
+            cluster.attributes.get_or_insert_with(|| Attributes(Vec::new())).0.push(Attribute {
+                r#type: PropertyType::IntVector,
+                name: Cow::Borrowed(META_INNER_EDGES),
+                value: Cow::Owned(Value::IntVector(inner).to_string())
+            });
+        }
+        self.children_mut(grandparent_id).expect("the parent of the parent").push(cluster);
+
+        let property = self.root_property_mut(VIEW_META_GRAPH, PropertyType::Graph, ("", "()"))
+            .expect("the type was checked above");
+        property.nodes_property.push(NodeProperty { id: metanode, value: Cow::Owned(cluster_id.to_string()) });
+        for (edge, edges) in meta_edges {
+            let value = Value::EdgeSet(edges).to_string();
+            property.edges_property.push(EdgeProperty { id: edge.id, value: Cow::Owned(value) });
+        }
+        Ok(metanode)
+    }
+
+    /// Expand the metanode `metanode`, undoing [Graph::collapse], and return its cluster.
+    ///
+    /// The metanode and its meta-edges are removed from every graph, along
+    /// with their values. The nodes of the cluster, its edges and the ones the
+    /// meta-edges stood for are put back in the graph of the metanode, which
+    /// the cluster is nested in again, with the edges of its [META_INNER_EDGES] attribute.
+    pub fn expand(&mut self, metanode: usize) -> Result<usize, String> {
+        let cluster = self.meta_graph(metanode)
+            .ok_or_else(|| format!("The node {metanode} is not a metanode"))?;
+        let cluster_id = cluster.id();
+        let grandparent_id = cluster.parent().map_or(0, |g| g.id());
+
+        // the graph of the metanode is a sibling of the cluster
+        let siblings: Vec<SubgraphRef> = match cluster.parent() {
+            Some(grandparent) => grandparent.children().collect(),
+            None => self.clusters().collect()
+        };
+        let parent_id = siblings.iter()
+            .find(|g| g.id() != cluster_id && g.nodes().contains(metanode))
+            .ok_or_else(|| format!("The metanode {metanode} is in no sibling of the cluster {cluster_id}"))?
+            .id();
+
+        let nodes: Ids = (**cluster.nodes()).clone();
+        let meta_ids: Ids = self.edges.iter()
+            .filter(|e| e.src == metanode || e.tgt == metanode)
+            .map(|e| e.id)
+            .collect();
+        let inner = match cluster.cluster().attributes.iter().flat_map(|a| &a.0).find(|a| a.name == META_INNER_EDGES) {
+            Some(attribute) => match Value::parse(&attribute.r#type, &attribute.value)? {
+                Value::IntVector(edges) => edges.into_iter().map(|e| e as usize).collect(),
+                value => return Err(format!("The attribute {META_INNER_EDGES:?} holds a {} value", value.property_type()))
+            },
+            None => Vec::new()
+        };
+        let restored: Ids = cluster.edges().iter()
+            .chain(meta_ids.iter().flat_map(|e| self.meta_edges(e).unwrap_or_default()))
+            .chain(inner)
+            .collect();
+        let removed = Ids::from(metanode..=metanode);
+
+        self.nodes.0 = self.nodes.difference(&removed);
+        self.edges.0.retain(|e| !meta_ids.contains(e.id));
+        for top in self.clusters.iter_mut().flat_map(|c| &mut c.0) {
+            top.for_each_mut(&mut |c| {
+                c.nodes.0 = c.nodes.difference(&removed);
+                c.edges.0 = c.edges.difference(&meta_ids);
+            });
+        }
+        for property in self.properties.iter_mut().flat_map(|p| &mut p.0) {
+            property.nodes_property.retain(|p| p.id != metanode);
+            property.edges_property.retain(|p| !meta_ids.contains(p.id));
+        }
+
+        let siblings = self.children_mut(grandparent_id).expect("the parent of the cluster");
+        let index = siblings.iter().position(|c| c.id == cluster_id).expect("a child of its parent");
+        let mut cluster = siblings.remove(index);
+        if let Some(attributes) = &mut cluster.attributes {
+            attributes.0.retain(|a| a.name != META_INNER_EDGES);
+            if attributes.0.is_empty() {
+                cluster.attributes = None;
+            }
+        }
+        let parent = self.cluster_mut(parent_id).expect("a sibling of the cluster");
+        parent.nodes.0 = parent.nodes.union(&nodes);
+        parent.edges.0 = parent.edges.union(&restored);
+        parent.clusters.push(cluster);
+        Ok(cluster_id)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..4)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(edge 3 0 2)
+(edge 4 3 4)
+(cluster 1
+(nodes 0..4)
+(edges 0..4)
+(cluster 2
+(nodes 1 2)
+(edges 1)
+)
+)
+(property  0 string "viewLabel"
+(default "" "")
+(node 1 "b")
+)
+)"#;
+
+    #[test]
+    fn test_collapse() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        let metanode = g.collapse(2).unwrap();
+        assert_eq!(metanode, 5);
+
+        assert_eq!(g.nodes().to_vec(), [0, 1, 2, 3, 4, 5]);
+        let view = g.find_cluster(1).unwrap();
+        assert_eq!(view.nodes().to_vec(), [0, 3, 4, 5]);
+        assert_eq!(view.edges().to_vec(), [4, 5, 6]);
+        assert_eq!(view.children().len(), 0);
+        // the cluster is now a sibling of the graph of its metanode
+        assert_eq!(g.clusters().map(|c| c.id()).collect::<Vec<_>>(), [1, 2]);
+
+        let meta: Vec<_> = g.edges_iter().skip(5).map(|e| (e.id, e.src, e.tgt)).collect();
+        assert_eq!(meta, [(5, 0, 5), (6, 5, 3)]);
+        assert_eq!(g.meta_edges(5), Some(vec![0, 3]));
+        assert_eq!(g.meta_edges(6), Some(vec![2]));
+        assert_eq!(g.meta_edges(4), None);
+        assert_eq!(g.meta_graph(5).unwrap().id(), 2);
+        assert!(g.meta_graph(0).is_none());
+
+        let written = g.to_string();
+        assert!(written.contains("(default \"\" \"()\")\n(node 5 \"2\")\n(edge 5 \"(0 3)\")\n"), "{written}");
+        let reread = Graph::parse_borrowed(&written).unwrap();
+        assert_eq!(reread.metanodes().map(|(n, c)| (n, c.id())).collect::<Vec<_>>(), [(5, 2)]);
+    }
+
+    #[test]
+    fn test_expand() {
+        let original = Graph::parse_borrowed(SOURCE).unwrap();
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        let metanode = g.collapse(2).unwrap();
+        assert_eq!(g.expand(metanode), Ok(2));
+
+        assert_eq!(g.nodes, original.nodes);
+        assert_eq!(g.edges, original.edges);
+        assert_eq!(g.clusters, original.clusters);
+        assert_eq!(g.metanodes().count(), 0);
+        assert!(g.expand(metanode).is_err());
+    }
+
+    #[test]
+    fn test_non_induced_cluster() {
+        // the edge 5 joins nodes of the cluster 2 without being its own, the edge 6 is only in the root
+        let source = r#"(tlp "2.3"
+(nodes 0..3)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(edge 3 3 0)
+(edge 4 0 2)
+(edge 5 2 1)
+(edge 6 1 1)
+(cluster 1
+(nodes 0..3)
+(edges 0..5)
+(cluster 2
+(nodes 1 2)
+(edges 1)
+)
+)
+)"#;
+        let original = Graph::parse_borrowed(source).unwrap();
+        let mut g = Graph::parse_borrowed(source).unwrap();
+        let metanode = g.collapse(2).unwrap();
+        assert_eq!(g.find_cluster(1).unwrap().edges().to_vec(), [3, 7, 8]);
+
+        // the inner edges are kept through a round trip to the file
+        let written = g.to_string();
+        assert!(written.contains("(graph_attributes 2 \n(vector<int> \"metaInnerEdges\" \"(5)\")\n)\n"), "{written}");
+        let mut g = Graph::parse_borrowed(&written).unwrap();
+        assert_eq!(g.expand(metanode), Ok(2));
+        assert_eq!(g.nodes, original.nodes);
+        assert_eq!(g.edges, original.edges);
+        assert_eq!(g.clusters, original.clusters);
+    }
+
+    #[test]
+    fn test_invalid_collapse() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        assert!(g.collapse(1).is_err());
+        assert!(g.collapse(7).is_err());
+        assert!(g.expand(1).is_err());
+
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        g.add_cluster(1, [2, 3]).unwrap();
+        assert!(g.collapse(2).is_err());
+        assert_eq!(g.nodes().len(), 5);
+    }
+}
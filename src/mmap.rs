@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::Graph;
+
+/// A tlp file mapped in memory.
+///
+/// The graphs parsed from it borrow their strings from the mapping, so
+/// neither the file nor its values are copied on the heap.
+pub struct MappedTlp {
+    map: Mmap
+}
+
+impl MappedTlp {
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the content has been checked to be valid UTF-8 when mapped
+        unsafe { std::str::from_utf8_unchecked(&self.map) }
+    }
+
+    pub fn graph(&self) -> Result<Graph<'_>, String> {
+        Graph::parse_borrowed(self.as_str())
+    }
+}
+
+impl Graph<'_> {
+    /// Map a tlp file in memory; the graph is then parsed with [MappedTlp::graph].
+    ///
+    /// As for any memory mapping, the file must not be modified while it is mapped.
+    pub fn open_mmap(path: impl AsRef<Path>) -> Result<MappedTlp, String> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+
+        // SAFETY: the mapping is read only, and the caller is warned that the file must not change underneath
+        let map = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("Unable to map {}: {e}", path.display()))?;
+
+        std::str::from_utf8(&map)
+            .map_err(|e| format!("{} is not valid UTF-8: {e}", path.display()))?;
+
+        Ok(MappedTlp { map })
+    }
+}
@@ -4,7 +4,7 @@ use petgraph;
 
 use crate::Graph;
 
-impl Graph {
+impl Graph<'_> {
     pub fn into_petgraph(&self) -> petgraph::Graph<usize, usize> {
         let mut g = petgraph::Graph::<usize, usize>::new();
 
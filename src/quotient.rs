@@ -0,0 +1,225 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::Edge;
+use crate::EdgeProperty;
+use crate::Edges;
+use crate::Graph;
+use crate::Ids;
+use crate::NodeProperty;
+use crate::NodesIds;
+use crate::Properties;
+use crate::Property;
+use crate::PropertyType;
+use crate::SubgraphRef;
+use crate::TlpVersion;
+use crate::Value;
+
+/// Name of the node property of a quotient graph holding the identifier of the cluster of each node.
+pub const QUOTIENT_CLUSTER: &str = "cluster";
+
+/// Name of the edge property of a quotient graph holding the number of edges between two clusters.
+pub const QUOTIENT_COUNT: &str = "count";
+
+/// Name of the edge property of a quotient graph holding the sum of the
+/// weights of the edges between two clusters, see [Graph::quotient].
+pub const QUOTIENT_WEIGHT: &str = "weight";
+
+/// Name of the edge property of a quotient graph holding the share of the
+/// possible edges between two clusters that are in the graph.
+pub const QUOTIENT_DENSITY: &str = "density";
+
+impl Graph<'_> {
+    /// The graph of the clusters nested directly in the graph `parent`, 0 being
+    /// the root: a node per cluster, in their order, and an edge between two
+    /// clusters when an edge of `parent` joins a node of one to a node of the other.
+    ///
+    /// The edges of the quotient keep the direction of the edges they stand
+    /// for, and the edges of `parent` in a single cluster are left out. Its
+    /// properties are [QUOTIENT_CLUSTER] for the nodes, [QUOTIENT_COUNT],
+    /// [QUOTIENT_WEIGHT] and [QUOTIENT_DENSITY] for the edges. The weight is
+    /// the sum of the values of the double property `weight`, as `parent`
+    /// sees it, or the count without one; the density is the count divided
+    /// by the number of pairs of nodes of the two clusters.
+    pub fn quotient(&self, parent: usize, weight: Option<&str>) -> Result<Graph<'static>, String> {
+        let (children, parent_edges): (Vec<SubgraphRef>, _) = if parent == 0 {
+            (self.clusters().collect(), None)
+        } else {
+            let cluster = self.find_cluster(parent)
+                .ok_or_else(|| format!("Unknown cluster {parent}"))?;
+            (cluster.children().collect(), Some(cluster.edges()))
+        };
+        if children.is_empty() {
+            return Err(format!("The graph {parent} has no clusters"));
+        }
+
+        // the clusters overlap, a node may be in several of them
+        let mut clusters_of: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, child) in children.iter().enumerate() {
+            for node in child.nodes().iter() {
+                clusters_of.entry(node).or_default().push(i);
+            }
+        }
+
+        let weight_of = match weight {
+            Some(name) => {
+                let property = self.visible_property(parent, name)
+                    .ok_or_else(|| format!("No property {name:?}"))?;
+                if property.r#type != PropertyType::Double {
+                    return Err(format!("The property {name:?} is not a {} property", PropertyType::Double));
+                }
+                Some(property.edge_values())
+            }
+            None => None
+        };
+
+        let mut totals: BTreeMap<(usize, usize), (usize, f64)> = BTreeMap::new();
+        let mut pairs = Vec::new();
+        for edge in self.edges.iter().filter(|e| parent_edges.is_none_or(|edges| edges.contains(e.id))) {
+            let (Some(sources), Some(targets)) = (clusters_of.get(&edge.src), clusters_of.get(&edge.tgt)) else {
+                continue;
+            };
+            pairs.clear();
+            for &a in sources {
+                for &b in targets.iter().filter(|&&b| b != a) {
+                    pairs.push((a, b));
+                }
+            }
+            pairs.sort_unstable();
+            pairs.dedup();
+            let edge_weight = match &weight_of {
+                Some(weight_of) => match weight_of(edge.id)? {
+                    Value::Double(v) => v,
+                    value => return Err(format!("Unexpected {} value", value.property_type()))
+                },
+                None => 1.
+            };
+            for pair in &pairs {
+                let (count, weight) = totals.entry(*pair).or_default();
+                *count += 1;
+                *weight += edge_weight;
+            }
+        }
+
+        let edges: Vec<Edge> = totals.keys()
+            .enumerate()
+            .map(|(id, &(src, tgt))| Edge { id, src, tgt })
+            .collect();
+        let property = |name: &str, r#type, default: &str| Property {
+            graph_id: 0,
+            name: Cow::Owned(name.to_string()),
+            r#type,
+            node_default: Cow::Owned(default.to_string()),
+            edge_default: Cow::Owned(default.to_string()),
+            nodes_property: Vec::new(),
+            edges_property: Vec::new()
+        };
+
+        let mut cluster = property(QUOTIENT_CLUSTER, PropertyType::Int, "0");
+        cluster.nodes_property = children.iter().enumerate()
+            .map(|(id, child)| NodeProperty { id, value: Cow::Owned(child.id().to_string()) })
+            .collect();
+
+        let mut count = property(QUOTIENT_COUNT, PropertyType::Int, "0");
+        let mut weight = property(QUOTIENT_WEIGHT, PropertyType::Double, "0");
+        let mut density = property(QUOTIENT_DENSITY, PropertyType::Double, "0");
+        for (id, (&(a, b), &(n, sum))) in totals.iter().enumerate() {
+            count.edges_property.push(EdgeProperty { id, value: Cow::Owned(n.to_string()) });
+            weight.edges_property.push(EdgeProperty { id, value: Cow::Owned(sum.to_string()) });
+            let pairs = children[a].nodes().len() * children[b].nodes().len();
+            let share = n as f64 / pairs as f64;
+            density.edges_property.push(EdgeProperty { id, value: Cow::Owned(share.to_string()) });
+        }
+
+        Ok(Graph {
+            version: TlpVersion::default(),
+            author: None,
+            comments: None,
+            date: None,
+            nodes: NodesIds(Ids::from(0..=children.len() - 1)),
+            edges: Edges(edges),
+            properties: Some(Properties(vec![cluster, count, weight, density])),
+            attributes: None,
+            clusters: None,
+            unknown: Vec::new(),
+            source_map: None
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+    use crate::Value;
+
+    use super::QUOTIENT_CLUSTER;
+    use super::QUOTIENT_COUNT;
+    use super::QUOTIENT_DENSITY;
+    use super::QUOTIENT_WEIGHT;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..5)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(edge 3 3 1)
+(edge 4 4 5)
+(edge 5 0 4)
+(edge 6 0 3)
+(cluster 3
+(nodes 0 1)
+(edges 0)
+)
+(cluster 5
+(nodes 2 3)
+(edges 2)
+)
+(cluster 7
+(nodes 4 5)
+(edges 4)
+)
+(property  0 double "traffic"
+(default "1" "0.5")
+(edge 1 "2")
+(edge 6 "3")
+)
+)"#;
+
+    #[test]
+    fn test_quotient() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        let q = g.quotient(0, None).unwrap();
+
+        assert_eq!(q.nodes().to_vec(), [0, 1, 2]);
+        // the edges 3 -> 5 and 5 -> 3 stay apart
+        let edges: Vec<_> = q.edges_iter().map(|e| (e.src, e.tgt)).collect();
+        assert_eq!(edges, [(0, 1), (0, 2), (1, 0)]);
+
+        let cluster = q.property(QUOTIENT_CLUSTER).unwrap();
+        assert_eq!(cluster.node_value(2), Ok(Value::Int(7)));
+        let count = q.property(QUOTIENT_COUNT).unwrap();
+        assert_eq!([0, 1, 2].map(|e| count.edge_value(e)), [Ok(Value::Int(2)), Ok(Value::Int(1)), Ok(Value::Int(1))]);
+        let weight = q.property(QUOTIENT_WEIGHT).unwrap();
+        assert_eq!(weight.edge_value(0), Ok(Value::Double(2.)));
+        let density = q.property(QUOTIENT_DENSITY).unwrap();
+        assert_eq!(density.edge_value(0), Ok(Value::Double(0.5)));
+        assert_eq!(density.edge_value(2), Ok(Value::Double(0.25)));
+
+        assert_eq!(Graph::parse_borrowed(&q.to_string()).unwrap(), q);
+        assert!(g.quotient(3, None).is_err());
+        assert!(g.quotient(4, None).is_err());
+    }
+
+    #[test]
+    fn test_weighted_quotient() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        let q = g.quotient(0, Some("traffic")).unwrap();
+        let weight = q.property(QUOTIENT_WEIGHT).unwrap();
+        // the edges without a value of their own weigh the default
+        assert_eq!([0, 1, 2].map(|e| weight.edge_value(e)), [Ok(Value::Double(5.)), Ok(Value::Double(0.5)), Ok(Value::Double(0.5))]);
+        assert_eq!(q.property(QUOTIENT_COUNT).unwrap().edge_value(0), Ok(Value::Int(2)));
+
+        assert!(g.quotient(0, Some("missing")).is_err());
+    }
+}
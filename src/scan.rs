@@ -0,0 +1,446 @@
+use std::borrow::Cow;
+
+use winnow::error::ParserError;
+use winnow::ModalResult;
+
+/// Run a hand written scanner over the input and consume what it read.
+///
+/// The lines repeated for each node or edge of a graph are too numerous
+/// to go through the generic combinators on large files: they are read in
+/// a single pass over the bytes with a [Cursor] instead. When the scanner
+/// gives up, nothing is consumed and a backtrack error is returned, as any
+/// other parser would do.
+pub(crate) fn scan<'i, O>(input: &mut &'i str, f: impl FnOnce(&mut Cursor<'i>) -> Option<O>) -> ModalResult<O> {
+    let mut cursor = Cursor::new(input);
+    match f(&mut cursor) {
+        Some(output) => {
+            *input = cursor.rest();
+            Ok(output)
+        }
+        None => Err(ParserError::from_input(input))
+    }
+}
+
+/// Apply `f` as long as it succeeds, skipping the whitespaces and comments after each success.
+/// The failing attempt consumes nothing.
+pub(crate) fn scan_repeat<'i>(c: &mut Cursor<'i>, mut f: impl FnMut(&mut Cursor<'i>) -> Option<()>) {
+    loop {
+        let mut attempt = c.clone();
+        if f(&mut attempt).is_none() {
+            break;
+        }
+        attempt.ws0();
+        *c = attempt;
+    }
+}
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// The 8 bytes from `pos`, the first one in the low byte.
+#[inline]
+fn word_at(bytes: &[u8], pos: usize) -> Option<u64> {
+    let chunk = bytes.get(pos..pos + 8)?;
+    Some(u64::from_le_bytes(chunk.try_into().ok()?))
+}
+
+/// The high bit of each byte of `word` that is not an ASCII digit.
+#[inline]
+fn non_digits(word: u64) -> u64 {
+    // with the high bits set, a subtraction never borrows from the next byte
+    let from_0 = (word | HIGH_BITS).wrapping_sub(ONES * b'0' as u64);
+    let from_10 = (word | HIGH_BITS).wrapping_sub(ONES * (b'9' + 1) as u64);
+    !(from_0 & !from_10 & !word) & HIGH_BITS
+}
+
+/// Move the high bit of each byte of `word` to the bit of the byte's index.
+#[inline]
+fn gather_high_bits(word: u64) -> u32 {
+    ((word >> 7).wrapping_mul(0x0102_0408_1020_4080) >> 56) as u32
+}
+
+/// The index of the first `byte` among the 32 first bytes of `chunk`, 32 if there is none.
+#[inline]
+fn first_byte(chunk: &[u8], byte: u8) -> usize {
+    let matches = |i: usize| {
+        let x = word_at(chunk, 8 * i).unwrap_or_default() ^ (ONES * byte as u64);
+        // a borrow only marks the bytes after a match, the first one is exact
+        (x.wrapping_sub(ONES) & !x & HIGH_BITS) as u128
+    };
+    let low = matches(1) << 64 | matches(0);
+    let high = matches(3) << 64 | matches(2);
+    if low != 0 {
+        low.trailing_zeros() as usize / 8
+    } else {
+        16 + high.trailing_zeros() as usize / 8
+    }
+}
+
+/// The index of the first `"` or `\\` from `pos`, looked for 8 bytes at a time.
+#[inline]
+fn find_quote_or_backslash(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    let matches = |byte: u8, word: u64| {
+        let x = word ^ (ONES * byte as u64);
+        // a borrow only marks the bytes after a match, the first one is exact
+        x.wrapping_sub(ONES) & !x & HIGH_BITS
+    };
+    while let Some(word) = word_at(bytes, pos) {
+        let found = matches(b'"', word) | matches(b'\\', word);
+        if found != 0 {
+            return Some(pos + found.trailing_zeros() as usize / 8);
+        }
+        pos += 8;
+    }
+    bytes[pos.min(bytes.len())..].iter()
+        .position(|&b| b == b'"' || b == b'\\')
+        .map(|len| pos + len)
+}
+
+/// The value of the `len` first bytes of `word`, which are digits.
+#[inline]
+fn digits_value(word: u64, len: usize) -> usize {
+    // move the digits to the high bytes, then combine them by pairs, quads and octets
+    let mut value = (word & 0x0f0f_0f0f_0f0f_0f0f) << (64 - 8 * len);
+    value = value.wrapping_mul(10 << 8 | 1) >> 8;
+    value = (value & 0x00ff_00ff_00ff_00ff).wrapping_mul(100 << 16 | 1) >> 16;
+    value = (value & 0x0000_ffff_0000_ffff).wrapping_mul(10000 << 32 | 1) >> 32;
+    value as usize
+}
+
+#[derive(Clone)]
+pub(crate) struct Cursor<'i> {
+    input: &'i str,
+    pos: usize
+}
+
+impl<'i> Cursor<'i> {
+    pub(crate) fn new(input: &'i str) -> Self {
+        Cursor { input, pos: 0 }
+    }
+
+    /// How many bytes were read.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// What remains to be read. The cursor only stops on char boundaries.
+    pub(crate) fn rest(&self) -> &'i str {
+        &self.input[self.pos..]
+    }
+
+    #[inline]
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    #[inline]
+    pub(crate) fn eat(&mut self, expected: u8) -> Option<()> {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn eat_str(&mut self, expected: &str) -> Option<()> {
+        if self.input.as_bytes()[self.pos..].starts_with(expected.as_bytes()) {
+            self.pos += expected.len();
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Skip spaces and tabs, inside a value where `;` is not a comment.
+    #[inline]
+    pub(crate) fn space0(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespaces and `;` comments, which run to the end of the line,
+    /// and tell how many bytes were skipped.
+    #[inline]
+    pub(crate) fn ws0(&mut self) -> usize {
+        let start = self.pos;
+        loop {
+            while matches!(self.peek(), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+                self.pos += 1;
+            }
+            if self.peek() != Some(b';') {
+                break;
+            }
+            self.skip_comment();
+        }
+        self.pos - start
+    }
+
+    /// Comments are rare, keep them out of the whitespace loop.
+    #[cold]
+    fn skip_comment(&mut self) {
+        self.pos += self.input[self.pos..].find('\n').map_or(self.input.len() - self.pos, |len| len + 1);
+    }
+
+    /// Skip at least one whitespace or comment.
+    #[inline]
+    pub(crate) fn ws1(&mut self) -> Option<()> {
+        // a single space is by far the most common separator
+        let bytes = self.input.as_bytes();
+        if bytes.get(self.pos) == Some(&b' ')
+            && !matches!(bytes.get(self.pos + 1), Some(b' ' | b'\t' | b'\r' | b'\n' | b';')) {
+            self.pos += 1;
+            return Some(());
+        }
+        (self.ws0() > 0).then_some(())
+    }
+
+    #[inline]
+    pub(crate) fn usize(&mut self) -> Option<usize> {
+        if let Some(value) = self.short_usize() {
+            return Some(value);
+        }
+
+        let start = self.pos;
+        let mut value: usize = 0;
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value.checked_mul(10)?
+                .checked_add((digit - b'0') as usize)?;
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(value)
+    }
+
+    /// Read an integer of less than 8 digits from the next 8 bytes at once,
+    /// which covers the identifiers of any graph that fits in memory.
+    #[inline]
+    fn short_usize(&mut self) -> Option<usize> {
+        let word = word_at(self.input.as_bytes(), self.pos)?;
+        let len = (non_digits(word).trailing_zeros() / 8) as usize;
+        if len == 0 || len == 8 {
+            return None;
+        }
+        self.pos += len;
+        Some(digits_value(word, len))
+    }
+
+    /// Read `N` integers of less than 8 digits, separated by single spaces
+    /// and followed by `close`, read too, from the next 32 bytes at once.
+    ///
+    /// This is how Tulip writes the edges: finding all the separators at
+    /// once spares waiting for the end of each integer to look for the next.
+    #[inline]
+    pub(crate) fn short_usizes<const N: usize>(&mut self, close: u8) -> Option<[usize; N]> {
+        let chunk = self.input.as_bytes().get(self.pos..self.pos + 32)?;
+        let mut ends = (0..4).fold(0, |ends, i| {
+            ends | gather_high_bits(non_digits(word_at(chunk, 8 * i).unwrap_or_default())) << (8 * i)
+        });
+
+        let mut values = [0; N];
+        let mut start = 0;
+        for (i, value) in values.iter_mut().enumerate() {
+            let end = ends.trailing_zeros() as usize;
+            let separator = if i + 1 == N { close } else { b' ' };
+            if !(1..8).contains(&end.wrapping_sub(start)) || chunk.get(end) != Some(&separator) {
+                return None;
+            }
+            *value = digits_value(word_at(chunk, start)?, end - start);
+            ends &= ends - 1;
+            start = end + 1;
+        }
+        // the position is taken from a search of its own, which does not wait for the values
+        let close_at = first_byte(chunk, close);
+        if close_at + 1 != start {
+            return None;
+        }
+        self.pos += close_at + 1;
+        Some(values)
+    }
+
+    /// Read the characters of a number, left to [str::parse] to decode:
+    /// digits, signs, dots, exponents, and the letters of `inf` or `nan`.
+    pub(crate) fn number(&mut self) -> Option<&'i str> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'+' | b'-' | b'.')) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| &self.input[start..self.pos])
+    }
+
+    /// Read a bare word: anything up to a whitespace, a parenthesis, a quote or a comment.
+    pub(crate) fn atom(&mut self) -> Option<&'i str> {
+        let start = self.pos;
+        while !matches!(self.peek(), None | Some(b' ' | b'\t' | b'\r' | b'\n' | b'(' | b')' | b'"' | b';')) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| &self.input[start..self.pos])
+    }
+
+    /// Read `"content"` and return the content, borrowed from the input
+    /// unless it holds escape sequences.
+    ///
+    /// A backslash escapes the character following it; `\n` and `\t` stand
+    /// for a new line and a tabulation.
+    #[inline]
+    pub(crate) fn string(&mut self) -> Option<Cow<'i, str>> {
+        self.eat(b'"')?;
+        let start = self.pos;
+        self.pos = find_quote_or_backslash(self.input.as_bytes(), start)?;
+        let len = self.pos - start;
+
+        if self.eat(b'"').is_some() {
+            return Some(Cow::Borrowed(&self.input[start..start + len]));
+        }
+
+        let mut unescaped = String::from(&self.input[start..self.pos]);
+        loop {
+            let end = find_quote_or_backslash(self.input.as_bytes(), self.pos)?;
+            unescaped.push_str(&self.input[self.pos..end]);
+            self.pos = end;
+
+            if self.eat(b'"').is_some() {
+                return Some(Cow::Owned(unescaped));
+            }
+
+            self.pos += 1;
+            let escaped = self.rest().chars().next()?;
+            unescaped.push(match escaped {
+                'n' => '\n',
+                't' => '\t',
+                c => c
+            });
+            self.pos += escaped.len_utf8();
+        }
+    }
+
+    /// Read `(tag` and the whitespaces following it, unless the tag is closed right away as in `(edges)`.
+    #[inline]
+    pub(crate) fn open_tag(&mut self, tag: &str) -> Option<()> {
+        self.eat(b'(')?;
+        self.ws0();
+        self.eat_str(tag)?;
+        if self.peek() == Some(b')') {
+            return Some(());
+        }
+        self.ws1()
+    }
+
+    /// Check, without consuming anything, that the input starts with `(tag` followed by a space.
+    #[inline]
+    pub(crate) fn peek_tag(&self, tag: &str) -> bool {
+        self.clone().open_tag(tag).is_some()
+    }
+
+    /// Read the `)` closing a tag, which, as for the other parsers, may be missing.
+    #[inline]
+    pub(crate) fn close_tag(&mut self) {
+        self.ws0();
+        let _ = self.eat(b')');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::Cursor;
+
+    #[test]
+    fn test_usize() {
+        let cases = [
+            ("0)", Some(0), ")"),
+            ("1234567 ", Some(1234567), " "),
+            ("12345678 ", Some(12345678), " "),
+            ("301404 61938", Some(301404), " 61938"),
+            ("42", Some(42), ""),
+            ("18446744073709551615)", Some(usize::MAX), ")"),
+            ("18446744073709551616)", None, ""),
+            ("x1", None, ""),
+        ];
+        for (repr, expected, rest) in cases {
+            let mut cursor = Cursor::new(repr);
+            assert_eq!(cursor.usize(), expected, "{repr}");
+            if expected.is_some() {
+                assert_eq!(cursor.rest(), rest, "{repr}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_usizes() {
+        let padding = " ".repeat(32);
+        let cases = [
+            ("0 1 2)", Some([0, 1, 2]), ""),
+            ("999999 1234567 42)\n", Some([999999, 1234567, 42]), "\n"),
+            ("1 2 3 )", None, ""),
+            ("1  2 3)", None, ""),
+            ("12345678 1 2)", None, ""),
+            ("1 x 3)", None, ""),
+            ("1 2 3 4)", None, ""),
+            ("1 2 3", None, ""),
+        ];
+        for (repr, expected, rest) in cases {
+            let input = format!("{repr}{padding}");
+            let mut cursor = Cursor::new(&input);
+            assert_eq!(cursor.short_usizes::<3>(b')'), expected, "{repr}");
+            if expected.is_some() {
+                assert_eq!(cursor.rest(), format!("{rest}{padding}"), "{repr}");
+            } else {
+                assert_eq!(cursor.rest(), input, "{repr}");
+            }
+        }
+        // too close to the end to read 32 bytes at once
+        assert_eq!(Cursor::new("0 1 2)").short_usizes::<3>(b')'), None);
+    }
+
+    #[test]
+    fn test_string() {
+        let cases = [
+            (r#""plain" rest"#, Some("plain"), true),
+            (r#""say \"hi\"""#, Some(r#"say "hi""#), false),
+            (r#""C:\\dir\\""#, Some(r"C:\dir\"), false),
+            (r#""two\nlines\tand tab""#, Some("two\nlines\tand tab"), false),
+            ("\"raw\nnew line\"", Some("raw\nnew line"), true),
+            (r#""(é, \ü) ✓""#, Some("(é, ü) ✓"), false),
+            (r#""longer than a word, \"quoted\" late""#, Some(r#"longer than a word, "quoted" late"#), false),
+            (r#""unterminated\""#, None, false),
+        ];
+        for (repr, expected, borrowed) in cases {
+            let value = Cursor::new(repr).string();
+            assert_eq!(value.as_deref(), expected, "{repr}");
+            assert_eq!(matches!(value, Some(Cow::Borrowed(_))), borrowed, "{repr}");
+        }
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut cursor = Cursor::new("(  edge\t1 2 3 )\n(edges 1)");
+        assert!(cursor.peek_tag("edge"));
+        assert!(cursor.open_tag("edge").is_some());
+        assert_eq!(cursor.rest(), "1 2 3 )\n(edges 1)");
+
+        let cursor = Cursor::new("(edges 1)");
+        assert!(!cursor.peek_tag("edge"));
+
+        let mut cursor = Cursor::new("(edges)");
+        assert!(cursor.open_tag("edges").is_some());
+        assert_eq!(cursor.rest(), ")");
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut cursor = Cursor::new(" ; a comment (edge 0 1 2)\n\t;another\n(edge");
+        assert_eq!(cursor.ws0(), 36);
+        assert_eq!(cursor.rest(), "(edge");
+
+        let mut cursor = Cursor::new(";unterminated");
+        assert!(cursor.ws1().is_some());
+        assert_eq!(cursor.rest(), "");
+
+        let mut cursor = Cursor::new("(edge;comment\n1 2 3)");
+        assert!(cursor.open_tag("edge").is_some());
+        assert_eq!(cursor.rest(), "1 2 3)");
+    }
+}
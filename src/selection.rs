@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+
+use crate::EdgeProperty;
+use crate::Graph;
+use crate::Ids;
+use crate::NodeProperty;
+use crate::Property;
+use crate::PropertyType;
+use crate::Value;
+use crate::VIEW_SELECTION;
+
+impl<'a> Graph<'a> {
+    /// The [VIEW_SELECTION] property of the root graph.
+    fn selection_property(&self) -> Option<&Property<'a>> {
+        self.properties.as_ref()?
+            .0.iter()
+            .find(|p| p.graph_id == 0 && p.name == VIEW_SELECTION && p.r#type == PropertyType::Bool)
+    }
+
+    /// The nodes selected in [VIEW_SELECTION], by a value of their own or by
+    /// default; none when the graph has no selection.
+    pub fn selected_nodes(&self) -> Ids {
+        let Some(property) = self.selection_property() else {
+            return Ids::default();
+        };
+        let value_of = property.node_values();
+        self.nodes.iter()
+            .filter(|&node| value_of(node) == Ok(Value::Bool(true)))
+            .collect()
+    }
+
+    /// The edges selected in [VIEW_SELECTION], see [Graph::selected_nodes].
+    pub fn selected_edges(&self) -> Ids {
+        let Some(property) = self.selection_property() else {
+            return Ids::default();
+        };
+        let value_of = property.edge_values();
+        self.edges.iter()
+            .map(|e| e.id)
+            .filter(|&edge| value_of(edge) == Ok(Value::Bool(true)))
+            .collect()
+    }
+
+    /// Select exactly the given nodes and edges, adding [VIEW_SELECTION] if missing.
+    ///
+    /// The default values become `false`, and the selected elements get a value of their own.
+    pub fn set_selection(&mut self, nodes: &Ids, edges: &Ids) -> Result<(), String> {
+        if let Some(node) = nodes.iter().find(|&node| !self.nodes.contains(node)) {
+            return Err(format!("Unknown node {node}"));
+        }
+        if let Some(edge) = edges.iter().find(|&edge| self.edge(edge).is_none()) {
+            return Err(format!("Unknown edge {edge}"));
+        }
+
+        let property = self.root_property_mut(VIEW_SELECTION, PropertyType::Bool, ("false", "false"))?;
+        property.node_default = Cow::Borrowed("false");
+        property.edge_default = Cow::Borrowed("false");
+        property.nodes_property = nodes.iter()
+            .map(|id| NodeProperty { id, value: Cow::Borrowed("true") })
+            .collect();
+        property.edges_property = edges.iter()
+            .map(|id| EdgeProperty { id, value: Cow::Borrowed("true") })
+            .collect();
+        Ok(())
+    }
+
+    /// Select nothing, see [Graph::set_selection].
+    pub fn clear_selection(&mut self) -> Result<(), String> {
+        self.set_selection(&Ids::default(), &Ids::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+    use crate::Ids;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..3)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(property  0 bool "viewSelection"
+(default "true" "false")
+(node 1 "false")
+(edge 2 "true")
+)
+)"#;
+
+    #[test]
+    fn test_read_selection() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        // selected by default, but the node 1
+        assert_eq!(g.selected_nodes().to_vec(), [0, 2, 3]);
+        assert_eq!(g.selected_edges().to_vec(), [2]);
+
+        let g = Graph::parse_borrowed("(tlp \"2.3\" (nodes 0 1) (edge 0 0 1))").unwrap();
+        assert!(g.selected_nodes().is_empty());
+        assert!(g.selected_edges().is_empty());
+    }
+
+    #[test]
+    fn test_write_selection() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        g.set_selection(&Ids::from(1..=2), &[0].into_iter().collect()).unwrap();
+        assert_eq!(g.selected_nodes().to_vec(), [1, 2]);
+        assert_eq!(g.selected_edges().to_vec(), [0]);
+        assert!(g.to_string().contains("(default \"false\" \"false\")\n(node 1 \"true\")\n(node 2 \"true\")\n(edge 0 \"true\")\n"));
+
+        g.clear_selection().unwrap();
+        assert!(g.selected_nodes().is_empty());
+        assert!(g.selected_edges().is_empty());
+
+        assert!(g.set_selection(&Ids::from(3..=4), &Ids::default()).is_err());
+        assert!(g.set_selection(&Ids::default(), &Ids::from(3..=3)).is_err());
+    }
+
+    #[test]
+    fn test_missing_selection() {
+        let mut g = Graph::parse_borrowed("(tlp \"2.3\" (nodes 0 1) (edge 0 0 1))").unwrap();
+        g.set_selection(&Ids::from(1..=1), &Ids::default()).unwrap();
+        assert_eq!(g.selected_nodes().to_vec(), [1]);
+
+        let written = g.to_string();
+        assert!(written.contains("(property  0 bool \"viewSelection\"\n(default \"false\" \"false\")\n(node 1 \"true\")\n)\n"), "{written}");
+        assert_eq!(Graph::parse_borrowed(&written).unwrap().selected_nodes().to_vec(), [1]);
+
+        let mut g = Graph::parse_borrowed("(tlp \"2.3\" (nodes 0) (property 0 int \"viewSelection\" (default \"0\" \"0\")\n))").unwrap();
+        assert!(g.clear_selection().is_err());
+    }
+}
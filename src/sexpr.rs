@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::Write;
+
+use crate::scan::Cursor;
+use crate::writer::write_string;
+
+/// A block of a tlp file read without knowing its meaning, see [crate::ParseOptions::lenient].
+#[derive(PartialEq, Debug, Clone)]
+pub enum SExpr<'a> {
+    /// A bare word or number, such as the tag of a block.
+    Atom(Cow<'a, str>),
+    /// A quoted string, unescaped.
+    String(Cow<'a, str>),
+    List(Vec<SExpr<'a>>)
+}
+
+impl SExpr<'_> {
+    /// The first atom of a list, which names the block.
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            SExpr::List(items) => match items.first()? {
+                SExpr::Atom(tag) => Some(tag),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    pub fn into_owned(self) -> SExpr<'static> {
+        match self {
+            SExpr::Atom(a) => SExpr::Atom(Cow::Owned(a.into_owned())),
+            SExpr::String(s) => SExpr::String(Cow::Owned(s.into_owned())),
+            SExpr::List(items) => SExpr::List(items.into_iter().map(SExpr::into_owned).collect()),
+        }
+    }
+}
+
+/// The known sections of a tlp file, in the order they are read and written.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(crate) enum Section {
+    Version,
+    Date,
+    Author,
+    Comments,
+    Nodes,
+    Edges,
+    Clusters,
+    Properties,
+    Attributes
+}
+
+/// A top-level block that is not part of the format, kept with the text it
+/// was read from so that it is written back untouched, comments included.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnknownBlock<'a> {
+    expr: SExpr<'a>,
+    source: Cow<'a, str>,
+    /// The known section the block follows, where it is written back.
+    pub(crate) after: Section
+}
+
+impl<'a> UnknownBlock<'a> {
+    pub(crate) fn new(expr: SExpr<'a>, source: &'a str, after: Section) -> Self {
+        UnknownBlock { expr, source: Cow::Borrowed(source), after }
+    }
+
+    pub fn expr(&self) -> &SExpr<'a> {
+        &self.expr
+    }
+
+    /// The text of the block, as it was read.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn into_owned(self) -> UnknownBlock<'static> {
+        UnknownBlock {
+            expr: self.expr.into_owned(),
+            source: Cow::Owned(self.source.into_owned()),
+            after: self.after
+        }
+    }
+}
+
+/// Read an atom, a string or a parenthesized list, skipping the comments inside lists.
+pub(crate) fn scan_sexpr<'i>(c: &mut Cursor<'i>) -> Option<SExpr<'i>> {
+    if c.eat(b'(').is_some() {
+        let mut items = Vec::new();
+        loop {
+            c.ws0();
+            if c.eat(b')').is_some() {
+                return Some(SExpr::List(items));
+            }
+            items.push(scan_sexpr(c)?);
+        }
+    }
+    if c.peek() == Some(b'"') {
+        return c.string().map(SExpr::String);
+    }
+    c.atom().map(|a| SExpr::Atom(Cow::Borrowed(a)))
+}
+
+/// Write the expression back as Tulip lays its blocks out: the nested
+/// lists each on their own line, and the closing parenthesis after them.
+impl fmt::Display for SExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SExpr::Atom(a) => f.write_str(a),
+            SExpr::String(s) => write_string(f, s),
+            SExpr::List(items) => {
+                f.write_char('(')?;
+                let mut nested = false;
+                for (i, item) in items.iter().enumerate() {
+                    if let SExpr::List(_) = item {
+                        f.write_char('\n')?;
+                        nested = true;
+                    } else if i > 0 {
+                        f.write_char(' ')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                if nested {
+                    f.write_char('\n')?;
+                }
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::scan::Cursor;
+
+    use super::scan_sexpr;
+    use super::SExpr;
+
+    #[test]
+    fn test_sexpr() {
+        let repr = "(controller 1 ; comment\n(view \"Node \\\"Link\\\"\" 0.5\n(data)\n)\n) rest";
+        let mut cursor = Cursor::new(repr);
+        let expr = scan_sexpr(&mut cursor).unwrap();
+        assert_eq!(cursor.rest(), " rest");
+        assert_eq!(expr.tag(), Some("controller"));
+
+        let SExpr::List(items) = &expr else { panic!() };
+        assert_eq!(items[1], SExpr::Atom(Cow::Borrowed("1")));
+        let SExpr::List(view) = &items[2] else { panic!() };
+        assert_eq!(view[1], SExpr::String(Cow::Borrowed("Node \"Link\"")));
+
+        assert_eq!(expr.to_string(), "(controller 1\n(view \"Node \\\"Link\\\"\" 0.5\n(data)\n)\n)");
+        let mut cursor = Cursor::new("(unclosed (list)");
+        assert!(scan_sexpr(&mut cursor).is_none());
+    }
+}
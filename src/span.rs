@@ -0,0 +1,327 @@
+/// Where an element was read: its bytes in the source, and the line it starts on (from 1).
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize
+}
+
+/// A [Span] in terms a user can look for in an editor.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Location<'s> {
+    pub line: usize,
+    /// In characters, from 1.
+    pub column: usize,
+    pub text: &'s str
+}
+
+impl Span {
+    /// Find the span in `source`, which must be the text it was read from.
+    pub fn locate<'s>(&self, source: &'s str) -> Location<'s> {
+        let line_start = source[..self.start].rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            line: self.line,
+            column: source[line_start..self.start].chars().count() + 1,
+            text: &source[self.start..self.end]
+        }
+    }
+}
+
+/// The spans of the values of a property.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PropertySpans {
+    graph_id: usize,
+    name: String,
+    span: Span,
+    nodes: Vec<(usize, Span)>,
+    edges: Vec<(usize, Span)>
+}
+
+impl PropertySpans {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The span of the `(node ...)` line of `node`, if it has a value of its own.
+    pub fn node(&self, node: usize) -> Option<Span> {
+        find(&self.nodes, node)
+    }
+
+    /// The span of the `(edge ...)` line of `edge`, if it has a value of its own.
+    pub fn edge(&self, edge: usize) -> Option<Span> {
+        find(&self.edges, edge)
+    }
+}
+
+/// The spans of the elements of a graph, recorded when [crate::ParseOptions::spans] is set.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SourceMap {
+    edges: Vec<(usize, Span)>,
+    clusters: Vec<(usize, Span)>,
+    properties: Vec<PropertySpans>,
+    attributes: Vec<(usize, String, Span)>
+}
+
+/// Look an element up by identifier; they are usually in increasing order, at their own index.
+fn find(spans: &[(usize, Span)], id: usize) -> Option<Span> {
+    spans.get(id)
+        .filter(|(i, _)| *i == id)
+        .or_else(|| spans.iter().find(|(i, _)| *i == id))
+        .map(|(_, span)| *span)
+}
+
+impl SourceMap {
+    pub fn edge(&self, edge: usize) -> Option<Span> {
+        find(&self.edges, edge)
+    }
+
+    pub fn cluster(&self, cluster: usize) -> Option<Span> {
+        find(&self.clusters, cluster)
+    }
+
+    /// The property `name` of the graph `graph_id`, 0 being the root.
+    pub fn property(&self, graph_id: usize, name: &str) -> Option<&PropertySpans> {
+        self.properties.iter()
+            .find(|p| p.graph_id == graph_id && p.name == name)
+    }
+
+    /// The attribute `name` of the graph `graph_id`, 0 being the root.
+    pub fn attribute(&self, graph_id: usize, name: &str) -> Option<Span> {
+        self.attributes.iter()
+            .find(|(id, n, _)| *id == graph_id && n == name)
+            .map(|(_, _, span)| *span)
+    }
+}
+
+/// Records the spans of the blocks while the parser reads them, when [crate::ParseOptions::spans] is set.
+///
+/// The parser only moves forward, so a position is given by the length of
+/// the input left to read: the offset is what has been read of the source.
+/// When it backtracks, the spans recorded since a [Checkpoint] are dropped.
+#[derive(Default)]
+pub(crate) struct SpanRecorder {
+    source_len: usize,
+    newlines: Vec<usize>,
+    map: Option<SourceMap>,
+    /// The values of the property being read, until it is complete.
+    nodes: Vec<(usize, Span)>,
+    edges: Vec<(usize, Span)>
+}
+
+/// How many spans were recorded at some point of the parse, see [SpanRecorder::rollback].
+#[derive(Clone, Copy)]
+pub(crate) struct Checkpoint {
+    edges: usize,
+    clusters: usize,
+    properties: usize,
+    attributes: usize,
+    node_values: usize,
+    edge_values: usize
+}
+
+impl SpanRecorder {
+    pub(crate) fn new(source: &str, enabled: bool) -> Self {
+        if !enabled {
+            return SpanRecorder::default();
+        }
+        SpanRecorder {
+            source_len: source.len(),
+            newlines: source.match_indices('\n').map(|(i, _)| i).collect(),
+            map: Some(SourceMap::default()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.map.is_some()
+    }
+
+    /// The span between the remaining lengths `before` and `after` reading an element.
+    fn span(&self, before: usize, after: usize) -> Span {
+        let start = self.source_len - before;
+        Span {
+            start,
+            end: self.source_len - after,
+            line: self.newlines.partition_point(|&i| i < start) + 1
+        }
+    }
+
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        let map = self.map.as_ref();
+        Checkpoint {
+            edges: map.map_or(0, |m| m.edges.len()),
+            clusters: map.map_or(0, |m| m.clusters.len()),
+            properties: map.map_or(0, |m| m.properties.len()),
+            attributes: map.map_or(0, |m| m.attributes.len()),
+            node_values: self.nodes.len(),
+            edge_values: self.edges.len()
+        }
+    }
+
+    /// Drop the spans recorded since `checkpoint`, their elements having been given up.
+    pub(crate) fn rollback(&mut self, checkpoint: Checkpoint) {
+        if let Some(map) = &mut self.map {
+            map.edges.truncate(checkpoint.edges);
+            map.clusters.truncate(checkpoint.clusters);
+            map.properties.truncate(checkpoint.properties);
+            map.attributes.truncate(checkpoint.attributes);
+        }
+        self.nodes.truncate(checkpoint.node_values);
+        self.edges.truncate(checkpoint.edge_values);
+    }
+
+    pub(crate) fn edge(&mut self, id: usize, before: usize, after: usize) {
+        if !self.enabled() {
+            return;
+        }
+        let span = self.span(before, after);
+        if let Some(map) = &mut self.map {
+            map.edges.push((id, span));
+        }
+    }
+
+    pub(crate) fn cluster(&mut self, id: usize, before: usize, after: usize) {
+        if !self.enabled() {
+            return;
+        }
+        let span = self.span(before, after);
+        if let Some(map) = &mut self.map {
+            map.clusters.push((id, span));
+        }
+    }
+
+    pub(crate) fn attribute(&mut self, graph_id: usize, name: &str, before: usize, after: usize) {
+        if !self.enabled() {
+            return;
+        }
+        let span = self.span(before, after);
+        if let Some(map) = &mut self.map {
+            map.attributes.push((graph_id, name.to_string(), span));
+        }
+    }
+
+    /// Record the value of a node, or of an edge, of the property being read.
+    pub(crate) fn property_value(&mut self, node: bool, id: usize, before: usize, after: usize) {
+        if self.enabled() {
+            let span = self.span(before, after);
+            if node { &mut self.nodes } else { &mut self.edges }.push((id, span));
+        }
+    }
+
+    /// Record the property just read, with the values recorded since the previous one.
+    pub(crate) fn property(&mut self, graph_id: usize, name: &str, before: usize, after: usize) {
+        if !self.enabled() {
+            return;
+        }
+        let span = self.span(before, after);
+        let nodes = std::mem::take(&mut self.nodes);
+        let edges = std::mem::take(&mut self.edges);
+        if let Some(map) = &mut self.map {
+            map.properties.push(PropertySpans { graph_id, name: name.to_string(), span, nodes, edges });
+        }
+    }
+
+    pub(crate) fn finish(self) -> Option<SourceMap> {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+    use crate::ParseOptions;
+
+    const SOURCE: &str = r#"(tlp "2.0"
+(nodes 0 1 2)
+(edge 0 0 1)
+  (edge 1 1 2)
+(cluster 1 "legacy"
+(nodes 0 1)
+(edges 0)
+(cluster 2
+(nodes 0)
+(edges )
+)
+)
+(property  0 string "viewLabel"
+(default "" "")
+(node 2 "é, \"b\"")
+(edge 1 "x")
+)
+(graph_attributes 2 
+(string "name" "sub")
+)
+)
+"#;
+
+    #[test]
+    fn test_spans() {
+        let g = Graph::parse_with(SOURCE, &ParseOptions { spans: true, ..Default::default() }).unwrap();
+        let map = g.source_map().unwrap();
+
+        let edge = map.edge(1).unwrap().locate(SOURCE);
+        assert_eq!((edge.line, edge.column, edge.text), (4, 3, "(edge 1 1 2)"));
+
+        assert_eq!(map.cluster(2).unwrap().line, 8);
+        assert_eq!(map.attribute(1, "name").unwrap().locate(SOURCE).text, "\"legacy\"");
+        assert_eq!(map.attribute(2, "name").unwrap().line, 19);
+
+        let label = map.property(0, "viewLabel").unwrap();
+        assert_eq!(label.span().line, 13);
+        assert_eq!(label.node(2).unwrap().locate(SOURCE).text, r#"(node 2 "é, \"b\"")"#);
+        assert_eq!(label.edge(1).unwrap().line, 16);
+        assert_eq!(label.node(0), None);
+
+        assert!(map.edge(5).is_none());
+        assert!(Graph::parse_borrowed(SOURCE).unwrap().source_map().is_none());
+    }
+
+    #[test]
+    fn test_column_in_characters() {
+        let source = "(tlp \"2.3\"\n(nodes 0)\n(property  0 string \"é\" (default \"\" \"\") (node 0 \"x\")))";
+        let g = Graph::parse_with(source, &ParseOptions { spans: true, ..Default::default() }).unwrap();
+        let node = g.source_map().unwrap().property(0, "é").unwrap().node(0).unwrap();
+        // the é takes two bytes but one column
+        assert_eq!(node.start - source.find("(property").unwrap(), 41);
+        assert_eq!(node.locate(source).column, 41);
+    }
+
+    #[test]
+    fn test_spans_as_lenient_as_the_parser() {
+        // the parser accepts the closing parentheses to be missing
+        let source = "(tlp \"2.3\"\n(nodes 0 1)\n(edge 0 0 1\n(property  0 string \"viewLabel\"\n(default \"\" \"\")\n(node 1 \"b\"";
+        let g = Graph::parse_with(source, &ParseOptions { spans: true, ..Default::default() }).unwrap();
+        let map = g.source_map().unwrap();
+        assert_eq!(map.edge(0).unwrap().line, 3);
+        let label = map.property(0, "viewLabel").unwrap();
+        assert_eq!(label.node(1).unwrap().locate(source).text, "(node 1 \"b\"");
+    }
+
+    #[test]
+    fn test_no_spans_of_malformed_blocks() {
+        // the first cluster is read up to its nodes before the parser gives it up
+        let source = r#"(tlp "2.0"
+(nodes 0 1)
+(cluster 2 "stale"
+(nodes x)
+(edges )
+)
+(cluster 2 "real"
+(nodes 1)
+(edges )
+)
+)
+"#;
+        let options = ParseOptions { spans: true, lenient: true, ..Default::default() };
+        let g = Graph::parse_with(source, &options).unwrap();
+        let map = g.source_map().unwrap();
+        assert_eq!(map.cluster(2).unwrap().line, 7);
+        assert_eq!(map.attribute(2, "name").unwrap().locate(source).text, "\"real\"");
+    }
+
+    #[test]
+    fn test_spans_left_out_of_equality() {
+        let with_spans = Graph::parse_with(SOURCE, &ParseOptions { spans: true, ..Default::default() }).unwrap();
+        assert_eq!(with_spans, Graph::parse_borrowed(SOURCE).unwrap());
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::slice;
+
+use crate::Attribute;
+use crate::Attributes;
+use crate::Cluster;
+use crate::Clusters;
+use crate::Edge;
+use crate::Edges;
+use crate::EdgesIds;
+use crate::Graph;
+use crate::Ids;
+use crate::NodesIds;
+use crate::Properties;
+use crate::Property;
+use crate::PropertyType;
+use crate::NAME;
+
+/// A cluster of a [Graph], with its place in the hierarchy of clusters.
+#[derive(Debug, Clone)]
+pub struct SubgraphRef<'g, 'a> {
+    /// The clusters from the top-level one down to this one, included.
+    path: Vec<&'g Cluster<'a>>
+}
+
+impl<'g, 'a> SubgraphRef<'g, 'a> {
+    fn top(cluster: &'g Cluster<'a>) -> Self {
+        SubgraphRef { path: vec![cluster] }
+    }
+
+    pub(crate) fn cluster(&self) -> &'g Cluster<'a> {
+        self.path[self.path.len() - 1]
+    }
+
+    fn child(&self, cluster: &'g Cluster<'a>) -> Self {
+        let mut path = Vec::with_capacity(self.path.len() + 1);
+        path.extend_from_slice(&self.path);
+        path.push(cluster);
+        SubgraphRef { path }
+    }
+
+    pub fn id(&self) -> usize {
+        self.cluster().id
+    }
+
+    /// The `name` attribute of its `graph_attributes`, or the name written
+    /// after its identifier in the older versions.
+    pub fn name(&self) -> Option<&'g str> {
+        self.cluster().name()
+    }
+
+    /// The cluster it is nested in, none for the clusters of the root graph.
+    pub fn parent(&self) -> Option<SubgraphRef<'g, 'a>> {
+        (self.path.len() > 1).then(|| SubgraphRef { path: self.path[..self.path.len() - 1].to_vec() })
+    }
+
+    /// The clusters nested directly in this one.
+    pub fn children(&self) -> impl DoubleEndedIterator<Item=SubgraphRef<'g, 'a>> + ExactSizeIterator + '_ {
+        self.cluster().clusters.iter().map(|c| self.child(c))
+    }
+
+    /// The clusters nested in this one, at any depth, each before the ones nested in it.
+    pub fn descendants(&self) -> PreOrder<'g, 'a> {
+        PreOrder { stack: self.children().rev().collect() }
+    }
+
+    /// The depth in the hierarchy, the root graph being at 0 and its clusters at 1.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn nodes(&self) -> &'g NodesIds {
+        &self.cluster().nodes
+    }
+
+    pub fn edges(&self) -> &'g EdgesIds {
+        &self.cluster().edges
+    }
+}
+
+impl PartialEq for SubgraphRef<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.cluster(), other.cluster())
+    }
+}
+
+/// Iterator over the clusters of a graph, each before the ones nested in it.
+#[derive(Debug, Clone)]
+pub struct PreOrder<'g, 'a> {
+    stack: Vec<SubgraphRef<'g, 'a>>
+}
+
+impl<'g, 'a> Iterator for PreOrder<'g, 'a> {
+    type Item = SubgraphRef<'g, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let subgraph = self.stack.pop()?;
+        self.stack.extend(subgraph.children().rev());
+        Some(subgraph)
+    }
+}
+
+impl FusedIterator for PreOrder<'_, '_> {}
+
+/// Iterator over the clusters of a graph, each after the ones nested in it.
+#[derive(Debug, Clone)]
+pub struct PostOrder<'g, 'a> {
+    roots: slice::Iter<'g, Cluster<'a>>,
+    /// The clusters being visited, with the number of their children already visited.
+    stack: Vec<(SubgraphRef<'g, 'a>, usize)>
+}
+
+impl<'g, 'a> Iterator for PostOrder<'g, 'a> {
+    type Item = SubgraphRef<'g, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stack.is_empty() {
+            self.stack.push((SubgraphRef::top(self.roots.next()?), 0));
+        }
+        loop {
+            let (subgraph, visited) = self.stack.last_mut()?;
+            match subgraph.cluster().clusters.get(*visited) {
+                Some(child) => {
+                    *visited += 1;
+                    let child = subgraph.child(child);
+                    self.stack.push((child, 0));
+                }
+                None => return self.stack.pop().map(|(subgraph, _)| subgraph)
+            }
+        }
+    }
+}
+
+impl FusedIterator for PostOrder<'_, '_> {}
+
+impl<'a> Graph<'a> {
+    fn top_clusters(&self) -> &[Cluster<'a>] {
+        self.clusters.as_ref().map_or(&[], |c| &c.0)
+    }
+
+    /// The clusters of the root graph, not the ones nested in them.
+    pub fn clusters(&self) -> impl DoubleEndedIterator<Item=SubgraphRef<'_, 'a>> + ExactSizeIterator {
+        self.top_clusters().iter().map(SubgraphRef::top)
+    }
+
+    /// All the clusters, each before the ones nested in it, in the order of the file.
+    pub fn clusters_pre_order(&self) -> PreOrder<'_, 'a> {
+        PreOrder { stack: self.clusters().rev().collect() }
+    }
+
+    /// All the clusters, each after the ones nested in it.
+    pub fn clusters_post_order(&self) -> PostOrder<'_, 'a> {
+        PostOrder { roots: self.top_clusters().iter(), stack: Vec::new() }
+    }
+
+    /// The cluster `id`, at any depth.
+    pub fn find_cluster(&self, id: usize) -> Option<SubgraphRef<'_, 'a>> {
+        self.clusters_pre_order().find(|c| c.id() == id)
+    }
+
+    /// The clusters, at any depth, that `node` belongs to: a node may be in
+    /// several clusters that are not nested in one another.
+    pub fn clusters_containing(&self, node: usize) -> impl Iterator<Item=SubgraphRef<'_, 'a>> {
+        self.clusters_pre_order().filter(move |c| c.nodes().contains(node))
+    }
+
+    /// The property `name` as the graph `graph_id` sees it, 0 being the root:
+    /// its own, or else the one of the nearest graph it is nested in, as in Tulip.
+    pub fn visible_property(&self, graph_id: usize, name: &str) -> Option<&Property<'a>> {
+        let mut graph_ids = vec![0];
+        if graph_id != 0 {
+            graph_ids.extend(self.find_cluster(graph_id)?.path.iter().map(|c| c.id));
+        }
+        let properties = &self.properties.as_ref()?.0;
+        graph_ids.iter().rev()
+            .find_map(|&id| properties.iter().find(|p| p.graph_id == id && p.name == name))
+    }
+
+    /// The cluster `cluster_id` as a graph of its own, which Tulip can open.
+    ///
+    /// The clusters nested in it are kept, under the new root. The properties
+    /// visible in the cluster, the ones of the root graph, of the clusters it
+    /// is nested in and its own, become properties of the new root, with the
+    /// values of the elements that are not in the cluster dropped; when several
+    /// of them have the same name, the nearest to the cluster wins, as in Tulip.
+    /// The attributes of the cluster become the ones of the new root.
+    pub fn extract_subgraph(&self, cluster_id: usize) -> Result<Graph<'a>, String> {
+        let subgraph = self.find_cluster(cluster_id)
+            .ok_or_else(|| format!("Unknown cluster {cluster_id}"))?;
+        let cluster = subgraph.cluster();
+
+        // the graphs the cluster inherits properties from, from the root down to itself
+        let inherited: Vec<usize> = std::iter::once(0)
+            .chain(subgraph.path.iter().map(|c| c.id))
+            .collect();
+        let nested: Vec<usize> = subgraph.descendants()
+            .map(|c| c.id())
+            .collect();
+
+        let mut visible: Vec<(usize, Property<'a>)> = Vec::new();
+        let mut local = Vec::new();
+        for property in self.properties.iter().flat_map(|p| &p.0) {
+            if let Some(depth) = inherited.iter().position(|&id| id == property.graph_id) {
+                let mut narrowed = property.clone();
+                narrowed.graph_id = 0;
+                narrowed.retain_elements(&cluster.nodes, &cluster.edges);
+
+                match visible.iter_mut().find(|(_, p)| p.name == property.name) {
+                    Some(shadowed) if shadowed.0 < depth => *shadowed = (depth, narrowed),
+                    Some(_) => {}
+                    None => visible.push((depth, narrowed))
+                }
+            } else if nested.contains(&property.graph_id) {
+                local.push(property.clone());
+            }
+        }
+        let properties: Vec<Property<'a>> = visible.into_iter()
+            .map(|(_, p)| p)
+            .chain(local)
+            .collect();
+
+        Ok(Graph {
+            version: self.version,
+            author: self.author.clone(),
+            comments: self.comments.clone(),
+            date: self.date.clone(),
+            nodes: cluster.nodes.clone(),
+            edges: Edges(self.edges.iter()
+                .filter(|e| cluster.edges.contains(e.id))
+                .map(Edge::clone)
+                .collect()),
+            properties: (!properties.is_empty()).then_some(Properties(properties)),
+            attributes: cluster.attributes.clone(),
+            clusters: (!cluster.clusters.is_empty()).then(|| Clusters(cluster.clusters.clone())),
+            unknown: Vec::new(),
+            source_map: None
+        })
+    }
+
+    /// Add a cluster of `nodes` to the graph `parent`, 0 being the root, with
+    /// the edges of `parent` between them, and return its identifier.
+    pub fn add_cluster(&mut self, parent: usize, nodes: impl IntoIterator<Item=usize>) -> Result<usize, String> {
+        let nodes: Ids = nodes.into_iter().collect();
+
+        let (parent_nodes, parent_edges) = if parent == 0 {
+            (&*self.nodes, None)
+        } else {
+            let cluster = self.find_cluster(parent)
+                .ok_or_else(|| format!("Unknown cluster {parent}"))?
+                .cluster();
+            (&*cluster.nodes, Some(&*cluster.edges))
+        };
+        if let Some(node) = nodes.iter().find(|&node| !parent_nodes.contains(node)) {
+            return Err(format!("The node {node} is not in the graph {parent}"));
+        }
+        let edges = self.edges.iter()
+            .filter(|e| parent_edges.is_none_or(|edges| edges.contains(e.id)))
+            .filter(|e| nodes.contains(e.src) && nodes.contains(e.tgt))
+            .map(|e| e.id)
+            .collect();
+
+        Ok(self.push_cluster(parent, nodes, edges))
+    }
+
+    /// Nest a new cluster of the given elements in the graph `parent`, which
+    /// must exist and hold them, and return its identifier.
+    pub(crate) fn push_cluster(&mut self, parent: usize, nodes: Ids, edges: Ids) -> usize {
+        let id = self.next_cluster_id();
+        self.insert_cluster(parent, Cluster {
+            id,
+            nodes: NodesIds(nodes),
+            edges: EdgesIds(edges),
+            attributes: None,
+            clusters: Vec::new()
+        });
+        id
+    }
+
+    /// Split the nodes of the root graph into clusters, one per value of
+    /// `key`, each with the edges between its nodes and named after its key.
+    /// Return the identifiers of the clusters by key.
+    pub fn partition_into_clusters<K: Ord + fmt::Display>(&mut self, mut key: impl FnMut(usize) -> K) -> BTreeMap<K, usize> {
+        // the parts by key, and their nodes and edges in the order of the nodes
+        let mut parts: BTreeMap<K, usize> = BTreeMap::new();
+        let mut members: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+        let mut part_of = HashMap::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            let part = *parts.entry(key(node)).or_insert(members.len());
+            if part == members.len() {
+                members.push((Vec::new(), Vec::new()));
+            }
+            members[part].0.push(node);
+            part_of.insert(node, part);
+        }
+        for edge in self.edges.iter() {
+            if let (Some(src), Some(tgt)) = (part_of.get(&edge.src), part_of.get(&edge.tgt))
+                && src == tgt {
+                members[*src].1.push(edge.id);
+            }
+        }
+
+        let first_id = self.next_cluster_id();
+        let mut ids = BTreeMap::new();
+        for (i, (key, part)) in parts.into_iter().enumerate() {
+            let id = first_id + i;
+            let (nodes, edges) = std::mem::take(&mut members[part]);
+            self.insert_cluster(0, Cluster {
+                id,
+                nodes: NodesIds(nodes.into_iter().collect()),
+                edges: EdgesIds(edges.into_iter().collect()),
+                attributes: Some(Attributes(vec![Attribute {
+                    r#type: PropertyType::String,
+                    name: NAME.into(),
+                    value: key.to_string().into()
+                }])),
+                clusters: Vec::new()
+            });
+            ids.insert(key, id);
+        }
+        ids
+    }
+
+    /// An identifier above the ones of all the clusters, the root being 0.
+    fn next_cluster_id(&self) -> usize {
+        self.clusters_pre_order().map(|c| c.id() + 1).max().unwrap_or(1)
+    }
+
+    /// Nest `cluster` in the graph `parent`, which must exist.
+    fn insert_cluster(&mut self, parent: usize, cluster: Cluster<'a>) {
+        self.children_mut(parent)
+            .expect("the parent was checked")
+            .push(cluster);
+    }
+
+    /// The cluster `id`, at any depth.
+    pub(crate) fn cluster_mut(&mut self, id: usize) -> Option<&mut Cluster<'a>> {
+        self.clusters.as_mut()?
+            .0.iter_mut()
+            .find_map(|c| c.find_mut(id))
+    }
+
+    /// The clusters nested directly in the graph `graph_id`, 0 being the root.
+    pub(crate) fn children_mut(&mut self, graph_id: usize) -> Option<&mut Vec<Cluster<'a>>> {
+        if graph_id == 0 {
+            return Some(&mut self.clusters.get_or_insert_with(|| Clusters(Vec::new())).0);
+        }
+        self.cluster_mut(graph_id).map(|c| &mut c.clusters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Graph;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..5)
+(edge 0 0 1)
+(edge 1 2 3)
+(cluster 1
+(nodes 0..3)
+(edges 0 1)
+(cluster 2
+(nodes 0 1)
+(edges 0)
+(cluster 3
+(nodes 0)
+(edges )
+)
+)
+(cluster 4
+(nodes 2 3)
+(edges 1)
+)
+)
+(cluster 5
+(nodes 3 4 5)
+(edges )
+)
+(graph_attributes 4
+(string "name" "right")
+)
+)"#;
+
+    #[test]
+    fn test_traversal() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        let ids = |clusters: &mut dyn Iterator<Item=crate::SubgraphRef>| clusters.map(|c| c.id()).collect::<Vec<_>>();
+
+        assert_eq!(ids(&mut g.clusters()), [1, 5]);
+        assert_eq!(ids(&mut g.clusters_pre_order()), [1, 2, 3, 4, 5]);
+        assert_eq!(ids(&mut g.clusters_post_order()), [3, 2, 4, 1, 5]);
+        assert_eq!(ids(&mut g.clusters_containing(3)), [1, 4, 5]);
+        assert_eq!(ids(&mut g.clusters_containing(6)), []);
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+
+        let deepest = g.find_cluster(3).unwrap();
+        assert_eq!(deepest.depth(), 3);
+        assert_eq!(deepest.nodes().to_vec(), [0]);
+        assert!(deepest.edges().is_empty());
+        assert_eq!(deepest.children().len(), 0);
+
+        let parent = deepest.parent().unwrap();
+        assert_eq!(parent.id(), 2);
+        assert_eq!(parent.parent().unwrap().parent(), None);
+        assert_eq!(parent.parent(), g.find_cluster(1));
+
+        let top = g.find_cluster(1).unwrap();
+        assert_eq!(top.depth(), 1);
+        assert_eq!(top.children().map(|c| c.id()).collect::<Vec<_>>(), [2, 4]);
+        assert_eq!(g.find_cluster(4).unwrap().name(), Some("right"));
+        assert_eq!(top.name(), None);
+        assert!(g.find_cluster(7).is_none());
+    }
+
+    #[test]
+    fn test_extract_subgraph() {
+        let source = r#"(tlp "2.3"
+(nodes 0..3)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(cluster 1
+(nodes 0..2)
+(edges 0 1)
+(cluster 2
+(nodes 1 2)
+(edges 1)
+)
+)
+(property  0 string "viewLabel"
+(default "" "")
+(node 0 "a")
+(node 3 "d")
+(edge 2 "x")
+)
+(property  0 double "weight"
+(default "0" "0")
+(node 1 "1")
+)
+(property  1 double "weight"
+(default "2" "0")
+(node 1 "3")
+)
+(property  2 int "local"
+(default "0" "0")
+)
+(property  3 int "elsewhere"
+(default "0" "0")
+)
+(graph_attributes 1
+(string "name" "one")
+)
+(graph_attributes 2
+(string "name" "two")
+)
+)"#;
+        let g = Graph::parse_borrowed(source).unwrap();
+        let sub = g.extract_subgraph(1).unwrap();
+
+        assert_eq!(sub.nodes().to_vec(), [0, 1, 2]);
+        assert_eq!(sub.edges_iter().map(|e| e.id).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(sub.clusters_pre_order().map(|c| c.id()).collect::<Vec<_>>(), [2]);
+        assert_eq!(sub.find_cluster(2).unwrap().name(), Some("two"));
+
+        let label = sub.property("viewLabel").unwrap();
+        assert_eq!(label.nodes_property.iter().map(|p| p.id).collect::<Vec<_>>(), [0]);
+        assert!(label.edges_property.is_empty());
+        // the property of the cluster shadows the one of the root
+        let weight = sub.property("weight").unwrap();
+        assert_eq!(weight.node_value(1), Ok(crate::Value::Double(3.)));
+        assert_eq!(weight.node_value(0), Ok(crate::Value::Double(2.)));
+
+        let written = sub.to_string();
+        assert!(written.contains("(property  2 int \"local\""), "{written}");
+        assert!(!written.contains("elsewhere"), "{written}");
+        assert!(written.contains("(graph_attributes 0 \n(string \"name\" \"one\")"), "{written}");
+        assert_eq!(Graph::parse_borrowed(&written).unwrap(), sub);
+
+        assert!(g.extract_subgraph(4).is_err());
+    }
+
+    #[test]
+    fn test_add_cluster() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+
+        let id = g.add_cluster(4, [3, 2]).unwrap();
+        assert_eq!(id, 6);
+        let added = g.find_cluster(id).unwrap();
+        assert_eq!(added.parent().unwrap().id(), 4);
+        assert_eq!(added.nodes().to_vec(), [2, 3]);
+        assert_eq!(added.edges().to_vec(), [1]);
+
+        // the edges of the root between the nodes, not only the ones of a cluster
+        let id = g.add_cluster(0, [0, 1, 5]).unwrap();
+        assert_eq!(g.find_cluster(id).unwrap().edges().to_vec(), [0]);
+
+        assert!(g.add_cluster(2, [0, 2]).is_err());
+        assert!(g.add_cluster(9, [0]).is_err());
+        assert_eq!(g.clusters_pre_order().count(), 7);
+
+        let written = g.to_string();
+        assert_eq!(Graph::parse_borrowed(&written).unwrap(), g);
+    }
+
+    #[test]
+    fn test_partition_into_clusters() {
+        let source = r#"(tlp "2.3"
+(nodes 0..4)
+(edge 0 0 1)
+(edge 1 1 2)
+(edge 2 2 3)
+(edge 3 3 4)
+(cluster 1
+(nodes 0)
+(edges )
+)
+(property  0 int "community"
+(default "0" "0")
+(node 2 "1")
+(node 3 "1")
+(node 4 "7")
+)
+)"#;
+        let mut g = Graph::parse_borrowed(source).unwrap();
+        let community = g.property("community").unwrap();
+        let communities: Vec<_> = g.nodes_iter()
+            .map(|node| community.node_value(node).unwrap().to_string())
+            .collect();
+
+        let ids = g.partition_into_clusters(|node| communities[node].parse::<i64>().unwrap());
+        assert_eq!(ids.into_iter().collect::<Vec<_>>(), [(0, 2), (1, 3), (7, 4)]);
+
+        let clusters: Vec<_> = g.clusters()
+            .map(|c| (c.id(), c.name(), c.nodes().to_vec(), c.edges().to_vec()))
+            .collect();
+        assert_eq!(clusters, [
+            (1, None, vec![0], vec![]),
+            (2, Some("0"), vec![0, 1], vec![0]),
+            (3, Some("1"), vec![2, 3], vec![2]),
+            (4, Some("7"), vec![4], vec![]),
+        ]);
+    }
+}
@@ -0,0 +1,314 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use crate::scan::Cursor;
+use crate::PropertyType;
+
+/// A color, written `(red,green,blue,alpha)` in a tlp file.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8
+}
+
+/// A position, written `(x,y,z)` in a tlp file.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Coord {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64
+}
+
+/// The dimensions of an element, written `(width,height,depth)` in a tlp file.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+    pub depth: f64
+}
+
+/// The decoded value of a property or an attribute, one variant per [PropertyType].
+///
+/// The values are kept as text in the graph, so that untouched ones are
+/// written back exactly as they were read; they are decoded on demand.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value<'a> {
+    Bool(bool),
+    Color(Color),
+    Double(f64),
+    /// Identifier of a cluster, 0 when there is none, which Tulip writes as an empty string.
+    Graph(usize),
+    Int(i64),
+    Coord(Coord),
+    String(Cow<'a, str>),
+    Size(Size),
+    BoolVector(Vec<bool>),
+    ColorVector(Vec<Color>),
+    DoubleVector(Vec<f64>),
+    IntVector(Vec<i64>),
+    CoordVector(Vec<Coord>),
+    SizeVector(Vec<Size>),
+    StringVector(Vec<Cow<'a, str>>),
+    /// Bends of an edge, the edge values of a [PropertyType::Layout] property.
+    Bends(Vec<Coord>),
+    /// Identifiers of edges, the edge values of a [PropertyType::Graph] property:
+    /// the edges a meta-edge stands for.
+    EdgeSet(Vec<usize>)
+}
+
+impl<'a> Value<'a> {
+    /// Decode `repr`, the content of a quoted value, as a value of type `r#type`.
+    pub fn parse(r#type: &PropertyType, repr: &'a str) -> Result<Self, String> {
+        if *r#type == PropertyType::String {
+            return Ok(Value::String(Cow::Borrowed(repr)));
+        }
+
+        Self::decode(r#type, repr, |c| match r#type {
+            PropertyType::Bool => boolean(c).map(Value::Bool),
+            PropertyType::Color => color(c).map(Value::Color),
+            PropertyType::Double => number(c).map(Value::Double),
+            PropertyType::Graph if c.rest().is_empty() => Some(Value::Graph(0)),
+            PropertyType::Graph => c.usize().map(Value::Graph),
+            PropertyType::Int => number(c).map(Value::Int),
+            PropertyType::Layout => coord(c).map(Value::Coord),
+            PropertyType::String => unreachable!(),
+            PropertyType::Size => size(c).map(Value::Size),
+            PropertyType::BoolVector => vector(c, boolean).map(Value::BoolVector),
+            PropertyType::ColorVector => vector(c, color).map(Value::ColorVector),
+            PropertyType::DoubleVector => vector(c, number).map(Value::DoubleVector),
+            PropertyType::IntVector => vector(c, number).map(Value::IntVector),
+            PropertyType::CoordVector => vector(c, coord).map(Value::CoordVector),
+            PropertyType::SizeVector => vector(c, size).map(Value::SizeVector),
+            PropertyType::StringVector => vector(c, Cursor::string).map(Value::StringVector),
+        })
+    }
+
+    /// Decode the value of an edge: as [Value::parse] does, except for the
+    /// [PropertyType::Layout] values which are the bends of the edge, and
+    /// the [PropertyType::Graph] values which are sets of edges.
+    pub fn parse_for_edge(r#type: &PropertyType, repr: &'a str) -> Result<Self, String> {
+        match r#type {
+            PropertyType::Layout => Self::decode(r#type, repr, |c| vector(c, coord).map(Value::Bends)),
+            PropertyType::Graph => Self::decode(r#type, repr, |c| edge_set(c).map(Value::EdgeSet)),
+            _ => Self::parse(r#type, repr)
+        }
+    }
+
+    fn decode(r#type: &PropertyType, repr: &'a str, f: impl FnOnce(&mut Cursor<'a>) -> Option<Self>) -> Result<Self, String> {
+        let mut c = Cursor::new(repr);
+        c.space0();
+        let value = f(&mut c);
+        c.space0();
+
+        match value {
+            Some(value) if c.rest().is_empty() => Ok(value),
+            _ => Err(format!("Invalid {type} value {repr:?}"))
+        }
+    }
+
+    pub fn property_type(&self) -> PropertyType {
+        match self {
+            Value::Bool(_) => PropertyType::Bool,
+            Value::Color(_) => PropertyType::Color,
+            Value::Double(_) => PropertyType::Double,
+            Value::Graph(_) => PropertyType::Graph,
+            Value::Int(_) => PropertyType::Int,
+            Value::Coord(_) => PropertyType::Layout,
+            Value::String(_) => PropertyType::String,
+            Value::Size(_) => PropertyType::Size,
+            Value::BoolVector(_) => PropertyType::BoolVector,
+            Value::ColorVector(_) => PropertyType::ColorVector,
+            Value::DoubleVector(_) => PropertyType::DoubleVector,
+            Value::IntVector(_) => PropertyType::IntVector,
+            Value::CoordVector(_) => PropertyType::CoordVector,
+            Value::SizeVector(_) => PropertyType::SizeVector,
+            Value::StringVector(_) => PropertyType::StringVector,
+            Value::Bends(_) => PropertyType::Layout,
+            Value::EdgeSet(_) => PropertyType::Graph,
+        }
+    }
+
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::String(s) => Value::String(Cow::Owned(s.into_owned())),
+            Value::StringVector(v) => Value::StringVector(v.into_iter().map(|s| Cow::Owned(s.into_owned())).collect()),
+            Value::Bool(v) => Value::Bool(v),
+            Value::Color(v) => Value::Color(v),
+            Value::Double(v) => Value::Double(v),
+            Value::Graph(v) => Value::Graph(v),
+            Value::Int(v) => Value::Int(v),
+            Value::Coord(v) => Value::Coord(v),
+            Value::Size(v) => Value::Size(v),
+            Value::BoolVector(v) => Value::BoolVector(v),
+            Value::ColorVector(v) => Value::ColorVector(v),
+            Value::DoubleVector(v) => Value::DoubleVector(v),
+            Value::IntVector(v) => Value::IntVector(v),
+            Value::CoordVector(v) => Value::CoordVector(v),
+            Value::SizeVector(v) => Value::SizeVector(v),
+            Value::Bends(v) => Value::Bends(v),
+            Value::EdgeSet(v) => Value::EdgeSet(v),
+        }
+    }
+}
+
+fn boolean(c: &mut Cursor) -> Option<bool> {
+    match c.number()? {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None
+    }
+}
+
+fn number<T: FromStr>(c: &mut Cursor) -> Option<T> {
+    c.number()?.parse().ok()
+}
+
+/// Read `(a,b,...)` with exactly `N` numeric components.
+fn components<T: FromStr + Default + Copy, const N: usize>(c: &mut Cursor) -> Option<[T; N]> {
+    c.eat(b'(')?;
+    let mut values = [T::default(); N];
+    for (i, value) in values.iter_mut().enumerate() {
+        c.space0();
+        if i > 0 {
+            c.eat(b',')?;
+            c.space0();
+        }
+        *value = number(c)?;
+    }
+    c.space0();
+    c.eat(b')')?;
+    Some(values)
+}
+
+fn color(c: &mut Cursor) -> Option<Color> {
+    let [r, g, b, a] = components(c)?;
+    Some(Color { r, g, b, a })
+}
+
+fn coord(c: &mut Cursor) -> Option<Coord> {
+    let [x, y, z] = components(c)?;
+    Some(Coord { x, y, z })
+}
+
+fn size(c: &mut Cursor) -> Option<Size> {
+    let [width, height, depth] = components(c)?;
+    Some(Size { width, height, depth })
+}
+
+/// Read `(item, item, ...)`, possibly empty.
+fn vector<'i, T>(c: &mut Cursor<'i>, mut item: impl FnMut(&mut Cursor<'i>) -> Option<T>) -> Option<Vec<T>> {
+    c.eat(b'(')?;
+    c.space0();
+    let mut items = Vec::new();
+    if c.eat(b')').is_some() {
+        return Some(items);
+    }
+    loop {
+        items.push(item(c)?);
+        c.space0();
+        if c.eat(b')').is_some() {
+            return Some(items);
+        }
+        c.eat(b',')?;
+        c.space0();
+    }
+}
+
+/// Read `(id id ...)`, possibly empty: unlike the vectors, there are no commas.
+fn edge_set(c: &mut Cursor) -> Option<Vec<usize>> {
+    c.eat(b'(')?;
+    let mut ids = Vec::new();
+    loop {
+        c.space0();
+        if c.eat(b')').is_some() {
+            return Some(ids);
+        }
+        ids.push(c.usize()?);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use crate::PropertyType;
+
+    use super::Color;
+    use super::Coord;
+    use super::Size;
+    use super::Value;
+
+    #[test]
+    fn test_parse_and_write() {
+        let cases = [
+            (PropertyType::Bool, "true", Value::Bool(true)),
+            (PropertyType::Color, "(255,0,128,255)", Value::Color(Color { r: 255, g: 0, b: 128, a: 255 })),
+            (PropertyType::Double, "-1.5", Value::Double(-1.5)),
+            (PropertyType::Graph, "3", Value::Graph(3)),
+            (PropertyType::Int, "-42", Value::Int(-42)),
+            (PropertyType::Layout, "(1,2.5,0)", Value::Coord(Coord { x: 1., y: 2.5, z: 0. })),
+            (PropertyType::Size, "(1,1,0)", Value::Size(Size { width: 1., height: 1., depth: 0. })),
+            (PropertyType::String, "a;b", Value::String(Cow::Borrowed("a;b"))),
+            (PropertyType::BoolVector, "(true, false)", Value::BoolVector(vec![true, false])),
+            (PropertyType::DoubleVector, "(1.5, 2, 3)", Value::DoubleVector(vec![1.5, 2., 3.])),
+            (PropertyType::DoubleVector, "()", Value::DoubleVector(vec![])),
+            (PropertyType::IntVector, "(1, -2)", Value::IntVector(vec![1, -2])),
+            (PropertyType::CoordVector, "((0,0,0), (1,2,3))", Value::CoordVector(vec![Coord::default(), Coord { x: 1., y: 2., z: 3. }])),
+            (PropertyType::StringVector, r#"("a", "b \"c\"")"#, Value::StringVector(vec![Cow::Borrowed("a"), Cow::Borrowed(r#"b "c""#)])),
+        ];
+        for (r#type, repr, expected) in cases {
+            let value = Value::parse(&r#type, repr).unwrap();
+            assert_eq!(value, expected, "{repr}");
+            assert_eq!(value.property_type(), r#type);
+            assert_eq!(value.to_string(), repr);
+        }
+    }
+
+    #[test]
+    fn test_bends() {
+        let value = Value::parse_for_edge(&PropertyType::Layout, "((1,2,0),(3,4,0))").unwrap();
+        assert_eq!(value, Value::Bends(vec![Coord { x: 1., y: 2., z: 0. }, Coord { x: 3., y: 4., z: 0. }]));
+        assert_eq!(value.property_type(), PropertyType::Layout);
+        assert_eq!(value.to_string(), "((1,2,0),(3,4,0))");
+
+        assert_eq!(Value::parse_for_edge(&PropertyType::Layout, "()").unwrap(), Value::Bends(vec![]));
+        assert!(Value::parse(&PropertyType::Layout, "()").is_err());
+        assert_eq!(Value::parse_for_edge(&PropertyType::Int, "3").unwrap(), Value::Int(3));
+    }
+
+    #[test]
+    fn test_meta_graph() {
+        assert_eq!(Value::parse(&PropertyType::Graph, "").unwrap(), Value::Graph(0));
+        assert_eq!(Value::Graph(0).to_string(), "");
+
+        let value = Value::parse_for_edge(&PropertyType::Graph, "(3 5 8 )").unwrap();
+        assert_eq!(value, Value::EdgeSet(vec![3, 5, 8]));
+        assert_eq!(value.property_type(), PropertyType::Graph);
+        assert_eq!(value.to_string(), "(3 5 8)");
+        assert_eq!(Value::parse_for_edge(&PropertyType::Graph, "()").unwrap(), Value::EdgeSet(vec![]));
+        assert!(Value::parse_for_edge(&PropertyType::Graph, "(3, 5)").is_err());
+    }
+
+    #[test]
+    fn test_lenient_spacing() {
+        let value = Value::parse(&PropertyType::ColorVector, " ( (1, 2,3,4) ,(5,6,7,8)) ").unwrap();
+        assert_eq!(value.to_string(), "((1,2,3,4), (5,6,7,8))");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let cases = [
+            (PropertyType::Bool, "yes"),
+            (PropertyType::Color, "(256,0,0,0)"),
+            (PropertyType::Color, "(0,0,0)"),
+            (PropertyType::Int, "1.5"),
+            (PropertyType::Layout, "(1,2,3) trailing"),
+            (PropertyType::DoubleVector, "(1, 2"),
+        ];
+        for (r#type, repr) in cases {
+            assert!(Value::parse(&r#type, repr).is_err(), "{repr}");
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the tlp format, the string following `tlp` in the header.
+///
+/// The older versions differ in how they write clusters and identifiers:
+/// up to 2.0 a cluster is named right after its identifier and the root
+/// graph has a `displaying` block, and before
+/// 2.2 the identifiers are all listed one by one.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default)]
+pub enum TlpVersion {
+    V2_0,
+    V2_1,
+    V2_2,
+    #[default]
+    V2_3
+}
+
+impl TlpVersion {
+    pub const SUPPORTED: [TlpVersion; 4] = [TlpVersion::V2_0, TlpVersion::V2_1, TlpVersion::V2_2, TlpVersion::V2_3];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlpVersion::V2_0 => "2.0",
+            TlpVersion::V2_1 => "2.1",
+            TlpVersion::V2_2 => "2.2",
+            TlpVersion::V2_3 => "2.3",
+        }
+    }
+
+    /// Whether consecutive identifiers may be written `first..last`.
+    pub fn has_ranges(&self) -> bool {
+        *self >= TlpVersion::V2_2
+    }
+
+    /// Whether the name of a cluster is written after its identifier,
+    /// instead of in its `graph_attributes`.
+    pub fn has_inline_cluster_names(&self) -> bool {
+        *self <= TlpVersion::V2_0
+    }
+
+    /// Whether the attributes of the root graph are written in a `displaying`
+    /// block, instead of in `graph_attributes 0`.
+    pub fn has_displaying(&self) -> bool {
+        *self <= TlpVersion::V2_0
+    }
+}
+
+impl FromStr for TlpVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TlpVersion::SUPPORTED.into_iter()
+            .find(|v| v.as_str() == s)
+            .ok_or_else(|| format!("Unsupported tlp version {s:?}, expected one of 2.0, 2.1, 2.2 or 2.3"))
+    }
+}
+
+impl fmt::Display for TlpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::TlpVersion;
+
+    #[test]
+    fn test_versions() {
+        for version in TlpVersion::SUPPORTED {
+            assert_eq!(TlpVersion::from_str(&version.to_string()), Ok(version));
+        }
+        assert!(TlpVersion::from_str("2.4").is_err());
+        assert!(TlpVersion::from_str("1.0").is_err());
+        assert!(TlpVersion::V2_0 < TlpVersion::V2_3);
+        assert!(!TlpVersion::V2_1.has_ranges());
+    }
+}
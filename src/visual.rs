@@ -0,0 +1,548 @@
+//! Typed access to the `view*` properties Tulip draws the graphs with.
+//!
+//! Each standard property is a [ViewProperty] constant, such as [COLOR] or
+//! [SHAPE], giving the types of its node and edge values.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use crate::EdgeProperty;
+use crate::Graph;
+use crate::NodeProperty;
+use crate::Property;
+use crate::PropertyType;
+use crate::Value;
+use crate::VIEW_LAYOUT;
+use crate::VIEW_SELECTION;
+use crate::value::Color;
+use crate::value::Coord;
+use crate::value::Size;
+
+mod mapping;
+
+pub use mapping::ColorScale;
+pub use mapping::Palette;
+pub use mapping::Scaling;
+
+/// A standard property of Tulip: its name, its type, its default values
+/// and the types `N` and `E` of its node and edge values.
+#[derive(PartialEq, Debug)]
+pub struct ViewProperty<N, E> {
+    standard: Standard,
+    values: PhantomData<fn() -> (N, E)>
+}
+
+#[derive(PartialEq, Debug)]
+struct Standard {
+    name: &'static str,
+    r#type: PropertyType,
+    /// `(node, edge)` default values, as Tulip writes them.
+    defaults: (&'static str, &'static str)
+}
+
+impl<N, E> ViewProperty<N, E> {
+    const fn new(name: &'static str, r#type: PropertyType, defaults: (&'static str, &'static str)) -> Self {
+        ViewProperty { standard: Standard { name, r#type, defaults }, values: PhantomData }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.standard.name
+    }
+}
+
+pub const BORDER_COLOR: ViewProperty<Color, Color> = ViewProperty::new("viewBorderColor", PropertyType::Color, ("(0,0,0,255)", "(0,0,0,255)"));
+pub const BORDER_WIDTH: ViewProperty<f64, f64> = ViewProperty::new("viewBorderWidth", PropertyType::Double, ("0", "0"));
+pub const COLOR: ViewProperty<Color, Color> = ViewProperty::new("viewColor", PropertyType::Color, ("(255,95,95,255)", "(180,180,180,255)"));
+pub const FONT: ViewProperty<String, String> = ViewProperty::new("viewFont", PropertyType::String, ("DejaVu Sans-Book", "DejaVu Sans-Book"));
+pub const FONT_SIZE: ViewProperty<i64, i64> = ViewProperty::new("viewFontSize", PropertyType::Int, ("18", "18"));
+pub const ICON: ViewProperty<String, String> = ViewProperty::new("viewIcon", PropertyType::String, ("fas-question-circle", "fas-question-circle"));
+pub const LABEL: ViewProperty<String, String> = ViewProperty::new("viewLabel", PropertyType::String, ("", ""));
+pub const LABEL_BORDER_COLOR: ViewProperty<Color, Color> = ViewProperty::new("viewLabelBorderColor", PropertyType::Color, ("(0,0,0,255)", "(0,0,0,255)"));
+pub const LABEL_BORDER_WIDTH: ViewProperty<f64, f64> = ViewProperty::new("viewLabelBorderWidth", PropertyType::Double, ("1", "1"));
+pub const LABEL_COLOR: ViewProperty<Color, Color> = ViewProperty::new("viewLabelColor", PropertyType::Color, ("(0,0,0,255)", "(0,0,0,255)"));
+pub const LABEL_POSITION: ViewProperty<LabelPosition, LabelPosition> = ViewProperty::new("viewLabelPosition", PropertyType::Int, ("0", "0"));
+pub const LABEL_ROTATION: ViewProperty<f64, f64> = ViewProperty::new("viewLabelRotation", PropertyType::Double, ("0", "0"));
+/// The positions of the nodes and the bends of the edges.
+pub const LAYOUT: ViewProperty<Coord, Vec<Coord>> = ViewProperty::new(VIEW_LAYOUT, PropertyType::Layout, ("(0,0,0)", "()"));
+pub const METRIC: ViewProperty<f64, f64> = ViewProperty::new("viewMetric", PropertyType::Double, ("0", "0"));
+pub const ROTATION: ViewProperty<f64, f64> = ViewProperty::new("viewRotation", PropertyType::Double, ("0", "0"));
+pub const SELECTION: ViewProperty<bool, bool> = ViewProperty::new(VIEW_SELECTION, PropertyType::Bool, ("false", "false"));
+pub const SHAPE: ViewProperty<NodeShape, EdgeShape> = ViewProperty::new("viewShape", PropertyType::Int, ("14", "0"));
+pub const SIZE: ViewProperty<Size, Size> = ViewProperty::new("viewSize", PropertyType::Size, ("(1,1,1)", "(0.125,0.125,0.5)"));
+/// The shape of the source end of the edges, the node values being unused.
+pub const SRC_ANCHOR_SHAPE: ViewProperty<i64, EdgeExtremityShape> = ViewProperty::new("viewSrcAnchorShape", PropertyType::Int, ("0", "-1"));
+pub const SRC_ANCHOR_SIZE: ViewProperty<Size, Size> = ViewProperty::new("viewSrcAnchorSize", PropertyType::Size, ("(1,1,0)", "(1,1,0)"));
+pub const TEXTURE: ViewProperty<String, String> = ViewProperty::new("viewTexture", PropertyType::String, ("", ""));
+/// The shape of the target end of the edges, the node values being unused.
+pub const TGT_ANCHOR_SHAPE: ViewProperty<i64, EdgeExtremityShape> = ViewProperty::new("viewTgtAnchorShape", PropertyType::Int, ("0", "50"));
+pub const TGT_ANCHOR_SIZE: ViewProperty<Size, Size> = ViewProperty::new("viewTgtAnchorSize", PropertyType::Size, ("(1,1,0)", "(1,1,0)"));
+
+/// The standard properties, in the order Tulip writes them.
+const STANDARD: [&Standard; 23] = [
+    &BORDER_COLOR.standard,
+    &BORDER_WIDTH.standard,
+    &COLOR.standard,
+    &FONT.standard,
+    &FONT_SIZE.standard,
+    &ICON.standard,
+    &LABEL.standard,
+    &LABEL_BORDER_COLOR.standard,
+    &LABEL_BORDER_WIDTH.standard,
+    &LABEL_COLOR.standard,
+    &LABEL_POSITION.standard,
+    &LABEL_ROTATION.standard,
+    &LAYOUT.standard,
+    &METRIC.standard,
+    &ROTATION.standard,
+    &SELECTION.standard,
+    &SHAPE.standard,
+    &SIZE.standard,
+    &SRC_ANCHOR_SHAPE.standard,
+    &SRC_ANCHOR_SIZE.standard,
+    &TEXTURE.standard,
+    &TGT_ANCHOR_SHAPE.standard,
+    &TGT_ANCHOR_SIZE.standard,
+];
+
+/// A type the values of a [ViewProperty] are decoded to.
+pub trait ViewValue: Sized {
+    fn from_value(value: Value<'_>) -> Result<Self, String>;
+
+    fn into_value(self) -> Value<'static>;
+}
+
+fn unexpected<T>(value: &Value) -> Result<T, String> {
+    Err(format!("Unexpected {} value", value.property_type()))
+}
+
+impl ViewValue for bool {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Bool(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Bool(self)
+    }
+}
+
+impl ViewValue for i64 {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Int(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Int(self)
+    }
+}
+
+impl ViewValue for f64 {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Double(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Double(self)
+    }
+}
+
+impl ViewValue for String {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::String(v) => Ok(v.into_owned()),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::String(Cow::Owned(self))
+    }
+}
+
+impl ViewValue for Color {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Color(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Color(self)
+    }
+}
+
+impl ViewValue for Coord {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Coord(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Coord(self)
+    }
+}
+
+impl ViewValue for Size {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Size(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Size(self)
+    }
+}
+
+/// The bends of an edge.
+impl ViewValue for Vec<Coord> {
+    fn from_value(value: Value<'_>) -> Result<Self, String> {
+        match value {
+            Value::Bends(v) => Ok(v),
+            value => unexpected(&value)
+        }
+    }
+
+    fn into_value(self) -> Value<'static> {
+        Value::Bends(self)
+    }
+}
+
+/// Implement [ViewValue] for an enum of integer codes, with `code` and `from_code`.
+macro_rules! int_codes {
+    ($name:ident, $what:literal) => {
+        impl $name {
+            /// The code Tulip writes for the value.
+            pub fn code(self) -> i64 {
+                self as i64
+            }
+
+            pub fn from_code(code: i64) -> Option<Self> {
+                $name::ALL.into_iter().find(|v| v.code() == code)
+            }
+        }
+
+        impl ViewValue for $name {
+            fn from_value(value: Value<'_>) -> Result<Self, String> {
+                let code = i64::from_value(value)?;
+                $name::from_code(code)
+                    .ok_or_else(|| format!(concat!("Unknown ", $what, " {}"), code))
+            }
+
+            fn into_value(self) -> Value<'static> {
+                Value::Int(self.code())
+            }
+        }
+    };
+}
+
+/// The shape of a node, the node values of [SHAPE].
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum NodeShape {
+    Cube = 0,
+    CubeOutlined = 1,
+    Sphere = 2,
+    Cone = 3,
+    Square = 4,
+    Diamond = 5,
+    Cylinder = 6,
+    Billboard = 7,
+    Cross = 8,
+    CubeOutlinedTransparent = 9,
+    HalfCylinder = 10,
+    Triangle = 11,
+    Pentagon = 12,
+    Hexagon = 13,
+    Circle = 14,
+    Ring = 15,
+    GlowSphere = 16,
+    Window = 17,
+    RoundedBox = 18,
+    Star = 19,
+    Icon = 20,
+    ChristmasTree = 28
+}
+
+impl NodeShape {
+    const ALL: [NodeShape; 22] = [
+        NodeShape::Cube, NodeShape::CubeOutlined, NodeShape::Sphere, NodeShape::Cone,
+        NodeShape::Square, NodeShape::Diamond, NodeShape::Cylinder, NodeShape::Billboard,
+        NodeShape::Cross, NodeShape::CubeOutlinedTransparent, NodeShape::HalfCylinder, NodeShape::Triangle,
+        NodeShape::Pentagon, NodeShape::Hexagon, NodeShape::Circle, NodeShape::Ring,
+        NodeShape::GlowSphere, NodeShape::Window, NodeShape::RoundedBox, NodeShape::Star,
+        NodeShape::Icon, NodeShape::ChristmasTree
+    ];
+}
+
+int_codes!(NodeShape, "node shape");
+
+/// The curve drawing an edge, the edge values of [SHAPE].
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum EdgeShape {
+    Polyline = 0,
+    BezierCurve = 4,
+    CatmullRomCurve = 8,
+    CubicBSplineCurve = 16
+}
+
+impl EdgeShape {
+    const ALL: [EdgeShape; 4] = [EdgeShape::Polyline, EdgeShape::BezierCurve, EdgeShape::CatmullRomCurve, EdgeShape::CubicBSplineCurve];
+}
+
+int_codes!(EdgeShape, "edge shape");
+
+/// The shape of an end of an edge, the edge values of [SRC_ANCHOR_SHAPE] and [TGT_ANCHOR_SHAPE].
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum EdgeExtremityShape {
+    None = -1,
+    Cube = 0,
+    CubeOutlinedTransparent = 1,
+    Sphere = 2,
+    Cone = 3,
+    Square = 4,
+    Diamond = 5,
+    Cylinder = 6,
+    Cross = 8,
+    Pentagon = 12,
+    Hexagon = 13,
+    Circle = 14,
+    Ring = 15,
+    GlowSphere = 16,
+    Star = 19,
+    Icon = 20,
+    Arrow = 50
+}
+
+impl EdgeExtremityShape {
+    const ALL: [EdgeExtremityShape; 17] = [
+        EdgeExtremityShape::None, EdgeExtremityShape::Cube, EdgeExtremityShape::CubeOutlinedTransparent,
+        EdgeExtremityShape::Sphere, EdgeExtremityShape::Cone, EdgeExtremityShape::Square,
+        EdgeExtremityShape::Diamond, EdgeExtremityShape::Cylinder, EdgeExtremityShape::Cross,
+        EdgeExtremityShape::Pentagon, EdgeExtremityShape::Hexagon, EdgeExtremityShape::Circle,
+        EdgeExtremityShape::Ring, EdgeExtremityShape::GlowSphere, EdgeExtremityShape::Star,
+        EdgeExtremityShape::Icon, EdgeExtremityShape::Arrow
+    ];
+}
+
+int_codes!(EdgeExtremityShape, "edge extremity shape");
+
+/// Where a label is drawn relative to its element, the values of [LABEL_POSITION].
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum LabelPosition {
+    Center = 0,
+    Top = 1,
+    Bottom = 2,
+    Left = 3,
+    Right = 4
+}
+
+impl LabelPosition {
+    const ALL: [LabelPosition; 5] = [LabelPosition::Center, LabelPosition::Top, LabelPosition::Bottom, LabelPosition::Left, LabelPosition::Right];
+}
+
+int_codes!(LabelPosition, "label position");
+
+impl<'a> Graph<'a> {
+    /// The property of the root graph standing for `standard`, none when missing;
+    /// an error if it has another type.
+    fn standard_property(&self, standard: &Standard) -> Result<Option<&Property<'a>>, String> {
+        let Some(property) = self.properties.iter()
+            .flat_map(|p| &p.0)
+            .find(|p| p.graph_id == 0 && p.name == standard.name) else {
+            return Ok(None);
+        };
+        if property.r#type != standard.r#type {
+            return Err(format!("The property {:?} is not a {} property", standard.name, standard.r#type));
+        }
+        Ok(Some(property))
+    }
+
+    /// The value of `node` in the standard property `view`, the default
+    /// one of Tulip when the graph does not have the property.
+    pub fn node_visual<N: ViewValue, E>(&self, view: &ViewProperty<N, E>, node: usize) -> Result<N, String> {
+        if !self.nodes.contains(node) {
+            return Err(format!("Unknown node {node}"));
+        }
+        let value = match self.standard_property(&view.standard)? {
+            Some(property) => property.node_value(node)?,
+            None => Value::parse(&view.standard.r#type, view.standard.defaults.0)?
+        };
+        N::from_value(value)
+    }
+
+    /// The value of `edge` in the standard property `view`, see [Graph::node_visual].
+    pub fn edge_visual<N, E: ViewValue>(&self, view: &ViewProperty<N, E>, edge: usize) -> Result<E, String> {
+        if self.edge(edge).is_none() {
+            return Err(format!("Unknown edge {edge}"));
+        }
+        let value = match self.standard_property(&view.standard)? {
+            Some(property) => property.edge_value(edge)?,
+            None => Value::parse_for_edge(&view.standard.r#type, view.standard.defaults.1)?
+        };
+        E::from_value(value)
+    }
+
+    /// Set the value of `node` in the standard property `view`, adding it
+    /// with the default values of Tulip if missing.
+    pub fn set_node_visual<N: ViewValue, E>(&mut self, view: &ViewProperty<N, E>, node: usize, value: N) -> Result<(), String> {
+        if !self.nodes.contains(node) {
+            return Err(format!("Unknown node {node}"));
+        }
+        let value = Cow::Owned(value.into_value().to_string());
+        let property = self.standard_property_mut(&view.standard)?;
+        match property.nodes_property.iter_mut().find(|p| p.id == node) {
+            Some(p) => p.value = value,
+            None => property.nodes_property.push(NodeProperty { id: node, value })
+        }
+        Ok(())
+    }
+
+    /// Set the value of `edge` in the standard property `view`, see [Graph::set_node_visual].
+    pub fn set_edge_visual<N, E: ViewValue>(&mut self, view: &ViewProperty<N, E>, edge: usize, value: E) -> Result<(), String> {
+        if self.edge(edge).is_none() {
+            return Err(format!("Unknown edge {edge}"));
+        }
+        let value = Cow::Owned(value.into_value().to_string());
+        let property = self.standard_property_mut(&view.standard)?;
+        match property.edges_property.iter_mut().find(|p| p.id == edge) {
+            Some(p) => p.value = value,
+            None => property.edges_property.push(EdgeProperty { id: edge, value })
+        }
+        Ok(())
+    }
+
+    /// Add the standard properties the graph does not have, with the default values of Tulip.
+    pub fn add_visual_properties(&mut self) -> Result<(), String> {
+        for standard in STANDARD {
+            self.standard_property_mut(standard)?;
+        }
+        Ok(())
+    }
+
+    fn standard_property_mut(&mut self, standard: &Standard) -> Result<&mut Property<'a>, String> {
+        self.root_property_mut(standard.name, standard.r#type.clone(), standard.defaults)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Color;
+    use crate::Coord;
+    use crate::Graph;
+    use crate::Size;
+    use super::EdgeExtremityShape;
+    use super::EdgeShape;
+    use super::LabelPosition;
+    use super::NodeShape;
+    use super::COLOR;
+    use super::FONT_SIZE;
+    use super::LABEL;
+    use super::LABEL_POSITION;
+    use super::LAYOUT;
+    use super::SHAPE;
+    use super::SIZE;
+    use super::SRC_ANCHOR_SHAPE;
+    use super::STANDARD;
+    use super::TGT_ANCHOR_SHAPE;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..2)
+(edge 0 0 1)
+(edge 1 1 2)
+(property  0 int "viewShape"
+(default "14" "0")
+(node 1 "4")
+(node 2 "99")
+(edge 1 "4")
+)
+(property  0 layout "viewLayout"
+(default "(0,0,0)" "()")
+(node 0 "(1,2,0)")
+(edge 0 "((0.5,1,0),(1,1,0))")
+)
+(property  0 int "viewTgtAnchorShape"
+(default "0" "50")
+(edge 1 "-1")
+)
+(property  0 string "viewColor"
+(default "red" "blue")
+)
+)"#;
+
+    #[test]
+    fn test_read_visual() {
+        let g = Graph::parse_borrowed(SOURCE).unwrap();
+        assert_eq!(g.node_visual(&SHAPE, 0), Ok(NodeShape::Circle));
+        assert_eq!(g.node_visual(&SHAPE, 1), Ok(NodeShape::Square));
+        assert_eq!(g.node_visual(&SHAPE, 2), Err("Unknown node shape 99".to_string()));
+        assert_eq!(g.edge_visual(&SHAPE, 1), Ok(EdgeShape::BezierCurve));
+
+        assert_eq!(g.node_visual(&LAYOUT, 0), Ok(Coord { x: 1., y: 2., z: 0. }));
+        assert_eq!(g.edge_visual(&LAYOUT, 0).unwrap().len(), 2);
+        assert_eq!(g.edge_visual(&TGT_ANCHOR_SHAPE, 0), Ok(EdgeExtremityShape::Arrow));
+        assert_eq!(g.edge_visual(&TGT_ANCHOR_SHAPE, 1), Ok(EdgeExtremityShape::None));
+
+        // the defaults of Tulip for the missing properties
+        assert_eq!(g.edge_visual(&SRC_ANCHOR_SHAPE, 0), Ok(EdgeExtremityShape::None));
+        assert_eq!(g.node_visual(&SIZE, 0), Ok(Size { width: 1., height: 1., depth: 1. }));
+        assert_eq!(g.node_visual(&LABEL_POSITION, 0), Ok(LabelPosition::Center));
+        assert_eq!(g.node_visual(&FONT_SIZE, 0), Ok(18));
+
+        assert!(g.node_visual(&COLOR, 0).is_err());
+        assert!(g.node_visual(&SHAPE, 3).is_err());
+        assert!(g.edge_visual(&SHAPE, 2).is_err());
+    }
+
+    #[test]
+    fn test_write_visual() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        g.set_node_visual(&SHAPE, 2, NodeShape::Triangle).unwrap();
+        g.set_edge_visual(&SHAPE, 0, EdgeShape::CubicBSplineCurve).unwrap();
+        g.set_edge_visual(&LAYOUT, 1, vec![Coord { x: 1.5, y: 0., z: 0. }]).unwrap();
+        assert_eq!(g.node_visual(&SHAPE, 2), Ok(NodeShape::Triangle));
+        assert_eq!(g.edge_visual(&SHAPE, 0), Ok(EdgeShape::CubicBSplineCurve));
+
+        // a missing property is added with the defaults of Tulip
+        g.set_node_visual(&LABEL_POSITION, 1, LabelPosition::Bottom).unwrap();
+        g.set_edge_visual(&LABEL, 0, "a \"b\"".to_string()).unwrap();
+        let written = g.to_string();
+        assert!(written.contains("(property  0 int \"viewLabelPosition\"\n(default \"0\" \"0\")\n(node 1 \"2\")\n)\n"), "{written}");
+        assert!(written.contains("(edge 1 \"((1.5,0,0))\")"), "{written}");
+
+        let read = Graph::parse_borrowed(&written).unwrap();
+        assert_eq!(read.node_visual(&LABEL_POSITION, 1), Ok(LabelPosition::Bottom));
+        assert_eq!(read.edge_visual(&LABEL, 0), Ok("a \"b\"".to_string()));
+        assert_eq!(read.edge_visual(&LAYOUT, 1), Ok(vec![Coord { x: 1.5, y: 0., z: 0. }]));
+
+        assert!(g.set_node_visual(&COLOR, 0, Color::default()).is_err());
+        assert!(g.set_node_visual(&SHAPE, 3, NodeShape::Cube).is_err());
+    }
+
+    #[test]
+    fn test_add_visual_properties() {
+        let mut g = Graph::parse_borrowed("(tlp \"2.3\" (nodes 0) (property 0 int \"viewShape\" (default \"4\" \"0\")\n))").unwrap();
+        g.add_visual_properties().unwrap();
+        assert_eq!(g.properties.as_ref().unwrap().0.len(), STANDARD.len());
+        // the existing properties are kept as they are
+        assert_eq!(g.node_visual(&SHAPE, 0), Ok(NodeShape::Square));
+        assert_eq!(g.node_visual(&COLOR, 0), Ok(Color { r: 255, g: 95, b: 95, a: 255 }));
+
+        for shape in NodeShape::ALL {
+            assert_eq!(NodeShape::from_code(shape.code()), Some(shape));
+        }
+        assert_eq!(EdgeExtremityShape::from_code(50), Some(EdgeExtremityShape::Arrow));
+        assert_eq!(LabelPosition::from_code(5), None);
+    }
+}
@@ -0,0 +1,380 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::Graph;
+use crate::NodeProperty;
+use crate::PropertyType;
+use crate::Value;
+use crate::value::Color;
+use crate::value::Size;
+use super::COLOR;
+use super::SIZE;
+
+/// How the values of a property are spread over a palette or a range of sizes.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Scaling {
+    /// In proportion to the value, between the smallest and the largest.
+    #[default]
+    Linear,
+    /// In proportion to the logarithm of the value, shifted to start at 1.
+    Log,
+    /// By the rank of the value, the equal values sharing the same one.
+    Quantile,
+    /// One step per distinct value, in the order they first appear;
+    /// the only scaling of the properties that are neither int nor double.
+    Categorical
+}
+
+/// The colors a [ColorScale] goes through.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum Palette {
+    /// From dark purple to yellow.
+    #[default]
+    Viridis,
+    /// From light yellow to dark red.
+    Heat,
+    /// From blue to red through white.
+    BlueRed,
+    /// From light to dark gray.
+    Grayscale,
+    /// Ten distinct colors, taken in turn by the categories rather than blended.
+    Category10,
+    /// The given colors, blended as the built-in gradients are.
+    Custom(Vec<Color>)
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 255 }
+}
+
+const VIRIDIS: [Color; 5] = [rgb(68, 1, 84), rgb(59, 82, 139), rgb(33, 145, 140), rgb(94, 201, 98), rgb(253, 231, 37)];
+const HEAT: [Color; 5] = [rgb(255, 255, 178), rgb(254, 204, 92), rgb(253, 141, 60), rgb(240, 59, 32), rgb(189, 0, 38)];
+const BLUE_RED: [Color; 5] = [rgb(33, 102, 172), rgb(103, 169, 207), rgb(247, 247, 247), rgb(239, 138, 98), rgb(178, 24, 43)];
+const GRAYSCALE: [Color; 2] = [rgb(230, 230, 230), rgb(20, 20, 20)];
+const CATEGORY10: [Color; 10] = [
+    rgb(31, 119, 180), rgb(255, 127, 14), rgb(44, 160, 44), rgb(214, 39, 40), rgb(148, 103, 189),
+    rgb(140, 86, 75), rgb(227, 119, 194), rgb(127, 127, 127), rgb(188, 189, 34), rgb(23, 190, 207)
+];
+
+impl Palette {
+    pub fn colors(&self) -> &[Color] {
+        match self {
+            Palette::Viridis => &VIRIDIS,
+            Palette::Heat => &HEAT,
+            Palette::BlueRed => &BLUE_RED,
+            Palette::Grayscale => &GRAYSCALE,
+            Palette::Category10 => &CATEGORY10,
+            Palette::Custom(colors) => colors
+        }
+    }
+
+    /// The color at `t`, from 0 for the first color to 1 for the last one.
+    fn at(&self, t: f64) -> Color {
+        let colors = self.colors();
+        let Some(last) = colors.len().checked_sub(1) else {
+            return Color::default();
+        };
+        let position = t.clamp(0., 1.) * last as f64;
+        let i = (position.floor() as usize).min(last.saturating_sub(1));
+        let (from, to) = (colors[i], colors[(i + 1).min(last)]);
+        let t = position - i as f64;
+        let blend = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Color { r: blend(from.r, to.r), g: blend(from.g, to.g), b: blend(from.b, to.b), a: blend(from.a, to.a) }
+    }
+
+    /// The color of the category `index` among `count`.
+    fn category(&self, index: usize, count: usize) -> Color {
+        match self {
+            Palette::Category10 => self.colors()[index % self.colors().len()],
+            _ => self.at(step(index, count))
+        }
+    }
+}
+
+/// How [Graph::map_color] colors the nodes.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ColorScale {
+    pub palette: Palette,
+    pub scaling: Scaling,
+    /// Palettes replacing `palette` for the nodes of some clusters, by
+    /// cluster identifier; the deepest cluster wins for the nested ones.
+    /// The values are scaled over the whole graph all the same.
+    pub cluster_palettes: Vec<(usize, Palette)>
+}
+
+/// The values of the nodes, as positions between 0 and 1, none for the
+/// values that are not finite, or as categories.
+enum Scaled {
+    Positions(Vec<Option<f64>>),
+    Categories(Vec<usize>, usize)
+}
+
+impl Scaled {
+    fn positions(self) -> Vec<Option<f64>> {
+        match self {
+            Scaled::Positions(positions) => positions,
+            Scaled::Categories(indices, count) => indices.into_iter().map(|i| Some(step(i, count))).collect()
+        }
+    }
+}
+
+/// The position of the step `index` among `count` evenly spaced ones.
+fn step(index: usize, count: usize) -> f64 {
+    if count > 1 { index as f64 / (count - 1) as f64 } else { 0. }
+}
+
+/// `values` moved to between 0 and 1, in proportion to the value.
+fn linear(values: &[f64]) -> Vec<f64> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values.iter()
+        .map(|v| if max > min { (v - min) / (max - min) } else { 0. })
+        .collect()
+}
+
+fn scale(values: &[Value], r#type: &PropertyType, scaling: Scaling) -> Scaled {
+    let numbers: Option<Vec<f64>> = match r#type {
+        PropertyType::Int | PropertyType::Double if scaling != Scaling::Categorical => values.iter()
+            .map(|v| match v {
+                Value::Int(v) => Some(*v as f64),
+                Value::Double(v) => Some(*v),
+                _ => None
+            })
+            .collect(),
+        _ => None
+    };
+    let Some(numbers) = numbers else {
+        let mut categories = HashMap::new();
+        let indices = values.iter()
+            .map(|v| {
+                let count = categories.len();
+                *categories.entry(v.to_string()).or_insert(count)
+            })
+            .collect();
+        return Scaled::Categories(indices, categories.len());
+    };
+
+    // nan and the infinities have no place on the scale
+    let finite: Vec<f64> = numbers.iter().copied().filter(|v| v.is_finite()).collect();
+    let mut positions = match scaling {
+        Scaling::Log => {
+            let min = finite.iter().copied().fold(f64::INFINITY, f64::min);
+            linear(&finite.iter().map(|v| (v - min + 1.).ln()).collect::<Vec<_>>())
+        }
+        Scaling::Quantile => {
+            let mut order: Vec<usize> = (0..finite.len()).collect();
+            order.sort_by(|&a, &b| finite[a].total_cmp(&finite[b]));
+            let mut ranks = vec![0.; finite.len()];
+            let mut start = 0;
+            while start < order.len() {
+                let end = start + order[start..].iter().take_while(|&&i| finite[i].total_cmp(&finite[order[start]]).is_eq()).count();
+                for &i in &order[start..end] {
+                    ranks[i] = (start + end - 1) as f64 / 2.;
+                }
+                start = end;
+            }
+            let last = finite.len().saturating_sub(1).max(1) as f64;
+            ranks.into_iter().map(|rank| rank / last).collect()
+        }
+        _ => linear(&finite)
+    }.into_iter();
+    Scaled::Positions(numbers.iter().map(|v| v.is_finite().then(|| positions.next()).flatten()).collect())
+}
+
+impl<'a> Graph<'a> {
+    /// The values of the root property `source` for all the nodes, their own or the default one, scaled.
+    fn scaled_nodes(&self, source: &str, scaling: Scaling) -> Result<Scaled, String> {
+        let property = self.visible_property(0, source)
+            .ok_or_else(|| format!("No property {source:?}"))?;
+        let value_of = property.node_values();
+        let values = self.nodes.iter()
+            .map(value_of)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(scale(&values, &property.r#type, scaling))
+    }
+
+    /// Color the nodes from the values of the property `source`, writing
+    /// [COLOR] for each of them, as the Color Mapping plugin of Tulip does.
+    ///
+    /// The int and double values are spread over the palette by `scale.scaling`,
+    /// the other values are categories; see [Scaling]. The nodes whose value is
+    /// nan or infinite are left with the default color.
+    pub fn map_color(&mut self, source: &str, scale: &ColorScale) -> Result<(), String> {
+        if let Some((cluster_id, _)) = scale.cluster_palettes.iter().find(|(id, _)| self.find_cluster(*id).is_none()) {
+            return Err(format!("Unknown cluster {cluster_id}"));
+        }
+        // the descendants come after their ancestors, and override them
+        let mut palettes = HashMap::new();
+        for cluster in self.clusters_pre_order() {
+            if let Some((_, palette)) = scale.cluster_palettes.iter().find(|(id, _)| *id == cluster.id()) {
+                palettes.extend(cluster.nodes().iter().map(|node| (node, palette)));
+            }
+        }
+
+        let scaled = self.scaled_nodes(source, scale.scaling)?;
+        let palette_of = |node| palettes.get(&node).copied().unwrap_or(&scale.palette);
+        let colors: Vec<(usize, Color)> = match scaled {
+            Scaled::Categories(indices, count) => self.nodes.iter().zip(indices)
+                .map(|(node, index)| (node, palette_of(node).category(index, count)))
+                .collect(),
+            Scaled::Positions(positions) => self.nodes.iter().zip(positions)
+                .filter_map(|(node, t)| Some((node, palette_of(node).at(t?))))
+                .collect()
+        };
+
+        let property = self.standard_property_mut(&COLOR.standard)?;
+        property.nodes_property = colors.into_iter()
+            .map(|(id, color)| NodeProperty { id, value: Cow::Owned(color.to_string()) })
+            .collect();
+        Ok(())
+    }
+
+    /// Size the nodes from the values of the property `source`, between the
+    /// bounds of `range`, writing [SIZE] for each of them; see [Graph::map_color].
+    pub fn map_size(&mut self, source: &str, range: RangeInclusive<Size>, scaling: Scaling) -> Result<(), String> {
+        let positions = self.scaled_nodes(source, scaling)?.positions();
+        let (min, max) = range.into_inner();
+        let blend = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let nodes = self.nodes.clone();
+        let property = self.standard_property_mut(&SIZE.standard)?;
+        property.nodes_property = nodes.iter().zip(positions)
+            .filter_map(|(id, t)| {
+                let t = t?;
+                let size = Size {
+                    width: blend(min.width, max.width, t),
+                    height: blend(min.height, max.height, t),
+                    depth: blend(min.depth, max.depth, t)
+                };
+                Some(NodeProperty { id, value: Cow::Owned(size.to_string()) })
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Color;
+    use crate::Graph;
+    use crate::Size;
+    use super::super::COLOR;
+    use super::super::SIZE;
+    use super::ColorScale;
+    use super::Palette;
+    use super::Scaling;
+
+    const SOURCE: &str = r#"(tlp "2.3"
+(nodes 0..4)
+(edge 0 0 1)
+(cluster 1
+(nodes 3 4)
+(edges )
+(cluster 2
+(nodes 4)
+(edges )
+)
+)
+(property  0 double "metric"
+(default "0" "0")
+(node 1 "1")
+(node 2 "9")
+(node 3 "9")
+(node 4 "99")
+)
+(property  0 string "kind"
+(default "a" "")
+(node 2 "b")
+(node 4 "c")
+)
+)"#;
+
+    const BLACK_WHITE: [Color; 2] = [Color { r: 0, g: 0, b: 0, a: 255 }, Color { r: 200, g: 200, b: 200, a: 255 }];
+
+    fn grays(g: &Graph) -> Vec<u8> {
+        g.nodes_iter().map(|node| g.node_visual(&COLOR, node).unwrap().r).collect()
+    }
+
+    #[test]
+    fn test_map_color() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        let mut scale = ColorScale { palette: Palette::Custom(BLACK_WHITE.to_vec()), ..Default::default() };
+        g.map_color("metric", &scale).unwrap();
+        assert_eq!(grays(&g), [0, 2, 18, 18, 200]);
+
+        scale.scaling = Scaling::Log;
+        g.map_color("metric", &scale).unwrap();
+        assert_eq!(grays(&g), [0, 30, 100, 100, 200]);
+
+        // the two 9 share the ranks 2 and 3
+        scale.scaling = Scaling::Quantile;
+        g.map_color("metric", &scale).unwrap();
+        assert_eq!(grays(&g), [0, 50, 125, 125, 200]);
+
+        scale.scaling = Scaling::Categorical;
+        g.map_color("metric", &scale).unwrap();
+        assert_eq!(grays(&g), [0, 67, 133, 133, 200]);
+
+        assert!(g.map_color("missing", &scale).is_err());
+        let written = g.to_string();
+        assert!(written.contains("(property  0 color \"viewColor\"\n(default \"(255,95,95,255)\" \"(180,180,180,255)\")\n(node 0 \"(0,0,0,255)\")\n"), "{written}");
+    }
+
+    #[test]
+    fn test_map_non_finite() {
+        let source = "(tlp \"2.3\" (nodes 0..4) (property  0 double \"metric\" (default \"0\" \"0\")\n(node 1 \"nan\") (node 2 \"inf\") (node 3 \"-inf\") (node 4 \"10\")))";
+        let mut g = Graph::parse_borrowed(source).unwrap();
+        let palette = Palette::Custom(BLACK_WHITE.to_vec());
+        for scaling in [Scaling::Linear, Scaling::Log, Scaling::Quantile] {
+            g.map_color("metric", &ColorScale { palette: palette.clone(), scaling, ..Default::default() }).unwrap();
+            // the values that are not finite keep the default color
+            let default = g.node_visual(&COLOR, 1).unwrap();
+            assert_eq!(default, Color { r: 255, g: 95, b: 95, a: 255 });
+            assert_eq!([2, 3].map(|node| g.node_visual(&COLOR, node).unwrap()), [default; 2]);
+            assert_eq!([0, 4].map(|node| g.node_visual(&COLOR, node).unwrap()), BLACK_WHITE);
+        }
+
+        g.map_size("metric", Size::default()..=Size { width: 2., height: 2., depth: 2. }, Scaling::Quantile).unwrap();
+        assert_eq!(g.node_visual(&SIZE, 0), Ok(Size::default()));
+        assert_eq!(g.node_visual(&SIZE, 4), Ok(Size { width: 2., height: 2., depth: 2. }));
+        assert_eq!(g.node_visual(&SIZE, 1), Ok(Size { width: 1., height: 1., depth: 1. }));
+    }
+
+    #[test]
+    fn test_map_categories() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        let scale = ColorScale { palette: Palette::Category10, ..Default::default() };
+        g.map_color("kind", &scale).unwrap();
+        let colors: Vec<_> = g.nodes_iter().map(|node| g.node_visual(&COLOR, node).unwrap()).collect();
+        let category10 = Palette::Category10.colors();
+        assert_eq!(colors, [category10[0], category10[0], category10[1], category10[0], category10[2]]);
+
+        // the deepest cluster wins
+        let scale = ColorScale {
+            palette: Palette::Custom(BLACK_WHITE.to_vec()),
+            scaling: Scaling::Linear,
+            cluster_palettes: vec![(2, Palette::Custom(vec![BLACK_WHITE[1]])), (1, Palette::Grayscale)]
+        };
+        g.map_color("kind", &scale).unwrap();
+        assert_eq!(grays(&g), [0, 0, 100, 230, 200]);
+
+        let unknown = ColorScale { cluster_palettes: vec![(3, Palette::Heat)], ..Default::default() };
+        assert!(g.map_color("kind", &unknown).is_err());
+    }
+
+    #[test]
+    fn test_map_size() {
+        let mut g = Graph::parse_borrowed(SOURCE).unwrap();
+        let range = Size { width: 1., height: 1., depth: 1. }..=Size { width: 11., height: 6., depth: 1. };
+        g.map_size("metric", range.clone(), Scaling::Quantile).unwrap();
+        let sizes: Vec<_> = g.nodes_iter().map(|node| g.node_visual(&SIZE, node).unwrap()).collect();
+        assert_eq!(sizes[0], Size { width: 1., height: 1., depth: 1. });
+        assert_eq!(sizes[2], Size { width: 7.25, height: 4.125, depth: 1. });
+        assert_eq!(sizes[4], Size { width: 11., height: 6., depth: 1. });
+
+        g.map_size("kind", range, Scaling::Linear).unwrap();
+        assert_eq!(g.node_visual(&SIZE, 2), Ok(Size { width: 6., height: 3.5, depth: 1. }));
+        assert_eq!(Graph::parse_borrowed(&g.to_string()).unwrap(), g);
+    }
+}
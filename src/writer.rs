@@ -0,0 +1,394 @@
+use std::fmt;
+use std::fmt::Write;
+
+use crate::Attributes;
+use crate::Cluster;
+use crate::Graph;
+use crate::Ids;
+use crate::Property;
+use crate::PropertyType;
+use crate::TlpVersion;
+use crate::NAME;
+use crate::sexpr::Section;
+use crate::value::Color;
+use crate::value::Coord;
+use crate::value::Size;
+use crate::value::Value;
+
+impl fmt::Display for PropertyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PropertyType::Bool => "bool",
+            PropertyType::Color => "color",
+            PropertyType::Double => "double",
+            PropertyType::Graph => "graph",
+            PropertyType::Int => "int",
+            PropertyType::Layout => "layout",
+            PropertyType::String => "string",
+            PropertyType::Size => "size",
+            PropertyType::BoolVector => "vector<bool>",
+            PropertyType::ColorVector => "vector<color>",
+            PropertyType::DoubleVector => "vector<double>",
+            PropertyType::IntVector => "vector<int>",
+            PropertyType::CoordVector => "vector<coord>",
+            PropertyType::SizeVector => "vector<size>",
+            PropertyType::StringVector => "vector<string>",
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({},{},{},{})", self.r, self.g, self.b, self.a)
+    }
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({},{},{})", self.x, self.y, self.z)
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({},{},{})", self.width, self.height, self.depth)
+    }
+}
+
+/// Write the content of a quoted value, as Tulip does: vectors are `(a, b, ...)`.
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Color(v) => write!(f, "{v}"),
+            Value::Double(v) => write!(f, "{v}"),
+            Value::Graph(0) => Ok(()),
+            Value::Graph(v) => write!(f, "{v}"),
+            Value::Int(v) => write!(f, "{v}"),
+            Value::Coord(v) => write!(f, "{v}"),
+            Value::String(v) => f.write_str(v),
+            Value::Size(v) => write!(f, "{v}"),
+            Value::BoolVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::ColorVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::DoubleVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::IntVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::CoordVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::SizeVector(v) => write_vector(f, v, |f, v| write!(f, "{v}")),
+            Value::StringVector(v) => write_vector(f, v, |f, v| write_string(f, v)),
+            Value::Bends(v) => {
+                // no space after the commas, unlike the vectors
+                f.write_char('(')?;
+                for (i, bend) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{bend}")?;
+                }
+                f.write_char(')')
+            }
+            Value::EdgeSet(v) => {
+                f.write_char('(')?;
+                for (i, edge) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(' ')?;
+                    }
+                    write!(f, "{edge}")?;
+                }
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+fn write_vector<T>(f: &mut fmt::Formatter<'_>, items: &[T], mut write_item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result) -> fmt::Result {
+    f.write_char('(')?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write_item(f, item)?;
+    }
+    f.write_char(')')
+}
+
+/// Write `s` between double quotes, escaping what [crate::parse_string] unescapes.
+pub(crate) fn write_string(out: &mut impl Write, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            _ => continue
+        };
+        out.write_str(&s[start..i])?;
+        out.write_str(escaped)?;
+        start = i + c.len_utf8();
+    }
+    out.write_str(&s[start..])?;
+    out.write_char('"')
+}
+
+/// How to write a graph, see [Graph::write_with].
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Version of the format to write, the one of the graph when unset.
+    pub version: Option<TlpVersion>
+}
+
+/// Write the graph with the same layout as Tulip, in its own version.
+/// Fails when the version cannot express the graph, see [Graph::write_with].
+impl fmt::Display for Graph<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, &WriteOptions::default()).map_err(|_| fmt::Error)
+    }
+}
+
+impl Graph<'_> {
+    /// Write the graph with the same layout as Tulip, following the
+    /// conventions of the version chosen in `options`.
+    ///
+    /// Up to tlp 2.0, a cluster has no attributes but its name: the graphs
+    /// with clusters carrying others cannot be written in those versions.
+    pub fn write_with(&self, f: &mut impl Write, options: &WriteOptions) -> Result<(), String> {
+        let version = options.version.unwrap_or(self.version);
+        if version.has_inline_cluster_names()
+            && let Some(cluster) = self.clusters_pre_order().find(|c| {
+                c.cluster().attributes.iter().flat_map(|a| &a.0).any(|a| a.name != NAME)
+            }) {
+            return Err(format!("The attributes of the cluster {} other than its name cannot be written in tlp {version}", cluster.id()));
+        }
+        self.write_in(f, version).map_err(|e| e.to_string())
+    }
+
+    fn write_in(&self, f: &mut impl Write, version: TlpVersion) -> fmt::Result {
+        f.write_str("(tlp ")?;
+        write_string(f, version.as_str())?;
+        f.write_char('\n')?;
+        self.write_unknown(f, Section::Version)?;
+
+        if let Some(date) = &self.date {
+            f.write_str("(date ")?;
+            write_string(f, &date.0)?;
+            f.write_str(")\n")?;
+        }
+        self.write_unknown(f, Section::Date)?;
+        if let Some(author) = &self.author {
+            f.write_str("(author ")?;
+            write_string(f, &author.0)?;
+            f.write_str(")\n")?;
+        }
+        self.write_unknown(f, Section::Author)?;
+        if let Some(comments) = &self.comments {
+            f.write_str("(comments ")?;
+            write_string(f, &comments.0)?;
+            f.write_str(")\n")?;
+        }
+        self.write_unknown(f, Section::Comments)?;
+
+        writeln!(f, "(nb_nodes {})", self.nodes.len())?;
+        writeln!(f, ";(nodes <node_id> <node_id> ...)")?;
+        f.write_str("(nodes ")?;
+        write_ids(f, &self.nodes, version)?;
+        f.write_str(")\n")?;
+        self.write_unknown(f, Section::Nodes)?;
+
+        writeln!(f, "(nb_edges {})", self.edges.len())?;
+        writeln!(f, ";(edge <edge_id> <source_id> <target_id>)")?;
+        for edge in self.edges.iter() {
+            writeln!(f, "(edge {} {} {})", edge.id, edge.src, edge.tgt)?;
+        }
+        self.write_unknown(f, Section::Edges)?;
+
+        if let Some(clusters) = &self.clusters {
+            for cluster in &clusters.0 {
+                write_cluster(f, cluster, version)?;
+            }
+        }
+        self.write_unknown(f, Section::Clusters)?;
+
+        if let Some(properties) = &self.properties {
+            for property in &properties.0 {
+                write_property(f, property)?;
+            }
+        }
+        self.write_unknown(f, Section::Properties)?;
+
+        if let Some(attributes) = &self.attributes {
+            if version.has_displaying() {
+                write_displaying(f, attributes)?;
+            } else {
+                write_attributes(f, 0, attributes, false)?;
+            }
+        }
+        if let Some(clusters) = &self.clusters {
+            for cluster in &clusters.0 {
+                write_clusters_attributes(f, cluster, version)?;
+            }
+        }
+
+        self.write_unknown(f, Section::Attributes)?;
+
+        f.write_str(")\n")
+    }
+
+    /// Write back, as they were read, the unknown blocks that followed the section `after`.
+    fn write_unknown(&self, f: &mut impl Write, after: Section) -> fmt::Result {
+        for block in self.unknown.iter().filter(|b| b.after == after) {
+            f.write_str(block.source())?;
+            f.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the identifiers as they are stored, or one by one when the version has no ranges.
+fn write_ids(out: &mut impl Write, ids: &Ids, version: TlpVersion) -> fmt::Result {
+    if version.has_ranges() {
+        return write!(out, "{ids}");
+    }
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            out.write_char(' ')?;
+        }
+        write!(out, "{id}")?;
+    }
+    Ok(())
+}
+
+fn write_cluster(out: &mut impl Write, cluster: &Cluster, version: TlpVersion) -> fmt::Result {
+    write!(out, "(cluster {}", cluster.id)?;
+    if version.has_inline_cluster_names()
+        && let Some(name) = cluster.name() {
+        out.write_char(' ')?;
+        write_string(out, name)?;
+    }
+    out.write_str("\n(nodes ")?;
+    write_ids(out, &cluster.nodes, version)?;
+    out.write_str(")\n(edges ")?;
+    write_ids(out, &cluster.edges, version)?;
+    out.write_str(")\n")?;
+    for sub_cluster in &cluster.clusters {
+        write_cluster(out, sub_cluster, version)?;
+    }
+    out.write_str(")\n")
+}
+
+/// Write the attributes of `cluster` and of its descendants, in the same order as the clusters.
+fn write_clusters_attributes(out: &mut impl Write, cluster: &Cluster, version: TlpVersion) -> fmt::Result {
+    if let Some(attributes) = &cluster.attributes {
+        write_attributes(out, cluster.id, attributes, version.has_inline_cluster_names())?;
+    }
+    for sub_cluster in &cluster.clusters {
+        write_clusters_attributes(out, sub_cluster, version)?;
+    }
+    Ok(())
+}
+
+fn write_property(out: &mut impl Write, property: &Property) -> fmt::Result {
+    write!(out, "(property  {} {} ", property.graph_id, property.r#type)?;
+    write_string(out, &property.name)?;
+
+    out.write_str("\n(default ")?;
+    write_string(out, &property.node_default)?;
+    out.write_char(' ')?;
+    write_string(out, &property.edge_default)?;
+    out.write_str(")\n")?;
+
+    for node in &property.nodes_property {
+        write!(out, "(node {} ", node.id)?;
+        write_string(out, &node.value)?;
+        out.write_str(")\n")?;
+    }
+    for edge in &property.edges_property {
+        write!(out, "(edge {} ", edge.id)?;
+        write_string(out, &edge.value)?;
+        out.write_str(")\n")?;
+    }
+
+    out.write_str(")\n")
+}
+
+/// Write the attributes of the root graph in a `displaying` block, as the older versions do.
+fn write_displaying(out: &mut impl Write, attributes: &Attributes) -> fmt::Result {
+    out.write_str("(displaying\n")?;
+    write_attributes_list(out, attributes, false)?;
+    out.write_str(")\n")
+}
+
+/// Write a `graph_attributes` block, without the name when it is already written with the cluster.
+fn write_attributes(out: &mut impl Write, graph_id: usize, attributes: &Attributes, skip_name: bool) -> fmt::Result {
+    if skip_name && attributes.0.iter().all(|a| a.name == NAME) {
+        return Ok(());
+    }
+
+    writeln!(out, "(graph_attributes {graph_id} ")?;
+    write_attributes_list(out, attributes, skip_name)?;
+    out.write_str(")\n")
+}
+
+fn write_attributes_list(out: &mut impl Write, attributes: &Attributes, skip_name: bool) -> fmt::Result {
+    for attribute in &attributes.0 {
+        if skip_name && attribute.name == NAME {
+            continue;
+        }
+        write!(out, "({} ", attribute.r#type)?;
+        write_string(out, &attribute.name)?;
+        out.write_char(' ')?;
+        write_string(out, &attribute.value)?;
+        out.write_str(")\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::write_string;
+    use crate::Graph;
+    use crate::TlpVersion;
+    use crate::WriteOptions;
+
+    #[test]
+    fn test_escaping() {
+        let cases = [
+            ("plain", r#""plain""#),
+            (r#"say "hi""#, r#""say \"hi\"""#),
+            (r"C:\dir\", r#""C:\\dir\\""#),
+            ("two\nlines", r#""two\nlines""#),
+            ("(é, ü) ✓", r#""(é, ü) ✓""#),
+        ];
+        for (value, expected) in cases {
+            let mut out = String::new();
+            write_string(&mut out, value).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_cluster_attributes_in_legacy_versions() {
+        let repr = r#"(tlp "2.3"
+(nodes 0 1)
+(cluster 1
+(nodes 0)
+(edges )
+)
+(graph_attributes 1 
+(string "name" "first")
+(int "size" "1")
+)
+)"#;
+        let g = Graph::parse_borrowed(repr).unwrap();
+        let legacy = WriteOptions { version: Some(TlpVersion::V2_0) };
+        let error = g.write_with(&mut String::new(), &legacy).unwrap_err();
+        assert!(error.contains("cluster 1"), "{error}");
+
+        // the name alone is written after the identifier
+        let repr = repr.replace("(int \"size\" \"1\")\n", "");
+        let named = Graph::parse_borrowed(&repr).unwrap();
+        let mut written = String::new();
+        named.write_with(&mut written, &legacy).unwrap();
+        assert!(written.contains("(cluster 1 \"first\"\n"), "{written}");
+        assert!(!written.contains("graph_attributes"), "{written}");
+    }
+}
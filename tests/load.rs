@@ -1,16 +1,32 @@
 use std::str::FromStr;
 
+use graphtlp::Coord;
+use graphtlp::Document;
 use graphtlp::Graph;
+use graphtlp::ParseOptions;
+use graphtlp::Value;
+use graphtlp::visual;
+use graphtlp::visual::EdgeExtremityShape;
+use graphtlp::visual::LabelPosition;
+use graphtlp::visual::NodeShape;
 
 #[test]
 fn load_complete() {
     let content = std::fs::read_to_string("data/complete.tlp").unwrap();
     let g = Graph::from_str(&content).unwrap();
+    assert_eq!(g.nodes().len(), 5);
+    assert_eq!(g.edges_iter().count(), 10);
+
+    let polyline = g.edge_polyline(0).unwrap();
+    assert_eq!(polyline.len(), 2);
+    assert_eq!((polyline[0].x, polyline[0].y), (11., -6.));
+    assert_eq!((polyline[1].x, polyline[1].y), (-2., -15.));
 
     #[cfg(feature="petgraph")]
     {
-        use graphtlp::petgraph;
         let p = g.into_petgraph();
+        assert_eq!(p.node_count(), 5);
+        assert_eq!(p.edge_count(), 10);
     }
 }
 
@@ -19,9 +35,108 @@ fn load_complete() {
 fn load_grid() {
     let content = std::fs::read_to_string("data/grid.tlp").unwrap();
     let g = Graph::from_str(&content).unwrap();
+    // a 10x10 grid
+    assert_eq!(g.nodes().len(), 100);
+    assert_eq!(g.edges_iter().count(), 180);
 
     #[cfg(feature="petgraph")]
     {
         let p = g.into_petgraph();
+        assert_eq!(p.node_count(), g.nodes().len());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature="mmap")]
+#[test]
+fn load_complete_mmap() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let map = Graph::open_mmap("data/complete.tlp").unwrap();
+    assert_eq!(map.as_str(), content);
+
+    let g = map.graph().unwrap();
+    assert_eq!(g, Graph::from_str(&content).unwrap());
+
+    assert!(Graph::open_mmap("data/missing.tlp").is_err());
+}
+
+#[test]
+fn write_complete() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let g = Graph::from_str(&content).unwrap();
+
+    // written back with the same layout as Tulip
+    assert_eq!(g.to_string(), content);
+}
+
+#[test]
+fn edit_complete() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let mut document = Document::parse(&content, &ParseOptions::default()).unwrap();
+    assert_eq!(document.to_string(), content);
+
+    document.set_node_value("viewLayout", 2, &Value::Coord(Coord { x: 1., y: 2., z: 0. })).unwrap();
+    let edited = document.to_string();
+
+    let changed: Vec<_> = content.lines().zip(edited.lines())
+        .filter(|(before, after)| before != after)
+        .collect();
+    assert_eq!(changed, [("(node 2 \"(6,10,0)\")", "(node 2 \"(1,2,0)\")")]);
+    assert_eq!(content.lines().count(), edited.lines().count());
+}
+
+#[test]
+fn load_windows_file() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let expected = Graph::from_str(&content).unwrap();
+
+    let path = std::env::temp_dir().join(format!("graphtlp-windows-{}.tlp", std::process::id()));
+    let windows = format!("\u{feff}{}", content.replace('\n', "\r\n"));
+    std::fs::write(&path, windows).unwrap();
+    assert_eq!(Graph::load(&path).unwrap(), expected);
+
+    // a Latin-1 label, not valid UTF-8
+    let latin1 = b"(tlp \"2.3\"\r\n(nodes 0)\r\n(property  0 string \"viewLabel\"\r\n(default \"\" \"\")\r\n(node 0 \"caf\xe9\")\r\n)\r\n)\r\n";
+    std::fs::write(&path, latin1).unwrap();
+    let g = Graph::load(&path).unwrap();
+    assert_eq!(g.property("viewLabel").unwrap().node_value(0), Ok(Value::String("café".into())));
+
+    let strict = ParseOptions { strict_encoding: true, ..Default::default() };
+    let error = Graph::load_with(&path, &strict).unwrap_err();
+    assert!(error.ends_with("Invalid UTF-8 at line 5 (byte 85)"), "{error}");
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(Graph::load(&path).is_err());
+}
+
+#[test]
+fn format_complete() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let formatted = graphtlp::format(&content).unwrap();
+    assert_eq!(graphtlp::format(&formatted).unwrap(), formatted);
+    // Talipot already writes it in the canonical form
+    assert_eq!(formatted, content);
+}
+
+#[test]
+fn visual_complete() {
+    let content = std::fs::read_to_string("data/complete.tlp").unwrap();
+    let g = Graph::from_str(&content).unwrap();
+    assert_eq!(g.node_visual(&visual::SHAPE, 0), Ok(NodeShape::Circle));
+    assert_eq!(g.edge_visual(&visual::TGT_ANCHOR_SHAPE, 0), Ok(EdgeExtremityShape::Arrow));
+    assert_eq!(g.node_visual(&visual::LABEL_POSITION, 4), Ok(LabelPosition::Center));
+    assert_eq!(g.node_visual(&visual::LAYOUT, 0), Ok(Coord { x: 11., y: -6., z: 0. }));
+
+    // the properties added to a bare graph are the ones Talipot writes
+    let mut bare = Graph::from_str("(tlp \"2.3\" (nodes 0))").unwrap();
+    bare.add_visual_properties().unwrap();
+    let names = content.lines()
+        .filter_map(|line| line.strip_prefix("(property  0 "))
+        .filter_map(|line| line.split('"').nth(1))
+        .filter(|&name| name != "viewMetaGraph");
+    for name in names {
+        let (expected, added) = (g.property(name).unwrap(), bare.property(name).unwrap());
+        assert_eq!(added.property_type(), expected.property_type(), "{name}");
+        assert_eq!(added.node_value(9), expected.node_value(9), "{name}");
+        assert_eq!(added.edge_value(9), expected.edge_value(9), "{name}");
+    }
+}